@@ -3,7 +3,34 @@
     Implementing the game of Connect 4
 */
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+use std::error;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use self::rules::{Move, Rules};
+
+// Submodules of connect_4 live in src/connect_4/. Being children, they can
+// see this module's private fields and functions.
+pub mod agent;
+pub mod analysis;
+pub mod bitboard;
+pub mod book;
+pub mod display;
+pub mod engine;
+pub mod evaluation;
+pub mod mcts;
+pub mod notation;
+pub mod perft;
+pub mod play;
+pub mod record;
+pub mod rng;
+pub mod rules;
+pub mod solver;
+pub mod tournament;
+pub mod transposition;
+pub mod zobrist;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 // Copy: the trait implemented by very simple, easily copyable
 // data like usize, u64, f64, &str
 // Essentially: Copy means "implicitly Clone me wherever needed"
@@ -12,16 +39,164 @@ pub enum Player {
     O,
 }
 
+impl Player {
+    pub fn other(self) -> Self {
+        match self {
+            Player::X => Player::O,
+            Player::O => Player::X,
+        }
+    }
+}
+
+// Errors are just another enum: one variant per way a move can be rejected.
+// Unlike debug_assert!, returning these works the same in --release mode.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BoardError {
+    ColumnOutOfRange(usize),
+    RowOutOfRange(usize),
+    ColumnFull(usize),
+    GameOver,
+    // The rules in play don't allow this move now (see rules.rs).
+    CantDrop(usize),
+    CantPop(usize),
+    // Under PopOut and Pop 10, only the player to move can play.
+    NotYourTurn(Player),
+}
+
+impl fmt::Display for BoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BoardError::ColumnOutOfRange(col) => {
+                write!(f, "column {} is off the board", col)
+            }
+            BoardError::RowOutOfRange(row) => {
+                write!(f, "row {} is off the board", row)
+            }
+            BoardError::ColumnFull(col) => write!(f, "column {} is full", col),
+            BoardError::GameOver => write!(f, "the game is already over"),
+            BoardError::CantDrop(col) => {
+                write!(f, "can't drop into column {} now", col)
+            }
+            BoardError::CantPop(col) => {
+                write!(f, "can't pop from column {} now", col)
+            }
+            BoardError::NotYourTurn(player) => {
+                write!(f, "it isn't {:?}'s turn", player)
+            }
+        }
+    }
+}
+
+impl error::Error for BoardError {}
+
+// Enum variants can have named fields, just like structs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum GameStatus {
+    InProgress { to_play: Player },
+    // cells: the winning line, e.g. for highlighting it.
+    Won { player: Player, cells: Vec<(usize, usize)> },
+    // Nobody won: under the classic rules, the board is full.
+    Draw,
+}
+
 const BOARD_LEN: usize = 10;
 const BOARD_HGT: usize = 5;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
+const CONNECT: usize = 4;
+
+// Right, up, up-right and down-right: every line on the board runs in one
+// of these directions (or the exact opposite).
+const DIRECTIONS: [(isize, isize); 4] = [(1, 0), (0, 1), (1, 1), (1, -1)];
+
+// The shape of the board, and how many in a row it takes to win.
+// Copy since it's just a few numbers.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct GameConfig {
+    pub width: usize,
+    pub height: usize,
+    pub connect: usize,
+}
+
+impl Default for GameConfig {
+    fn default() -> Self {
+        Self::new(BOARD_LEN, BOARD_HGT)
+    }
+}
+
+impl GameConfig {
+    // For other run lengths, use struct update syntax:
+    // GameConfig { connect: 5, ..GameConfig::new(9, 7) }
+    pub fn new(width: usize, height: usize) -> Self {
+        Self { width, height, connect: CONNECT }
+    }
+
+    // The 7 wide, 6 tall board you'd buy in a store.
+    pub fn standard() -> Self {
+        Self::new(7, 6)
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Game {
-    board: [Vec<Player>; BOARD_LEN],
-    // Alternative: Vec<Vec<Player>>, but
-    // the above is technically a bit more accurate as we don't need dynamic
-    // modification.
+    config: GameConfig,
+    rules: Rules,
+    board: Vec<Vec<Player>>,
+    // We used to use [Vec<Player>; BOARD_LEN], which is a bit more accurate
+    // as we don't need dynamic modification. But the length of an array
+    // has to be known at compile time, and the width now comes from config.
     to_play: Player,
+    // Cached so that winner() and status() don't have to rescan the whole
+    // board: updated by each play, looking only at the new piece.
+    won: Option<(Player, Vec<(usize, usize)>)>,
+    // Likewise whether a PopOut or Pop 10 game has been drawn, which takes
+    // the history and every move to work out. Classic games are drawn
+    // when the board is full, which filled already tells us.
+    drawn: bool,
+    filled: usize,
+    // Where in history a pop set a piece aside, under Pop 10.
+    set_aside: Vec<usize>,
+    // Every play so far, oldest first, and the plays undone since the last
+    // new one (most recently undone last).
+    history: Vec<(Move, Player)>,
+    redo: Vec<(Move, Player)>,
+    // Whose turn it was before each play in history. Under the classic
+    // rules play() lets either player move, so this isn't always the
+    // player who made the play, and undo has to put it back as it was.
+    turns: Vec<Player>,
+    // Zobrist hashes of this position and of its mirror image, kept up to
+    // date by each play and undo. See zobrist.rs.
+    hash: u64,
+    mirror_hash: u64,
+    // The hash before each play in history, for spotting repetitions.
+    hashes: Vec<u64>,
+}
+
+// Two games are equal if they have the same pieces in the same places, and
+// the same player to move. The cached fields are left out: they follow from
+// the rest, although won might hold a different line of the same winner.
+// So is the history: we care about where we are, not how we got here.
+// Under Pop 10 that includes the pieces set aside, and whether a popped
+// piece still has to go back in.
+impl PartialEq for Game {
+    fn eq(&self, other: &Self) -> bool {
+        self.config == other.config
+            && self.rules == other.rules
+            && self.board == other.board
+            && self.to_play == other.to_play
+            && self.captured(Player::X) == other.captured(Player::X)
+            && self.captured(Player::O) == other.captured(Player::O)
+            && self.pending_drop() == other.pending_drop()
+    }
+}
+
+impl Eq for Game {}
+
+// Equal games have equal Zobrist hashes, so we can hash with that instead
+// of walking the whole board.
+impl Hash for Game {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.hash);
+    }
 }
 
 // Instead of using the #[derive(...)] we can also implement traits manually
@@ -29,7 +204,7 @@ pub struct Game {
 // For example: default for usize is 0, default for Vec is Vec::new() i.e. vec![]
 impl Default for Game {
     fn default() -> Self {
-        Self { board: Default::default(), to_play: Player::X }
+        Self::with_config(Default::default())
     }
 }
 
@@ -41,30 +216,262 @@ impl Game {
         Default::default()
     }
 
-    // Note: we shouldn't require ourselves to take &self
-    // as a parameter if it isn't needed! E.g.:
-    fn in_range(col: usize, row: usize) -> bool {
-        col <= BOARD_LEN && row <= BOARD_HGT
+    pub fn with_config(config: GameConfig) -> Self {
+        Self::with_rules(config, Rules::Classic)
+    }
+
+    pub fn with_rules(config: GameConfig, rules: Rules) -> Self {
+        assert!(config.width > 0 && config.height > 0, "empty board");
+        assert!(config.connect > 0, "need at least one in a row to win");
+        Self {
+            config,
+            rules,
+            board: vec![Vec::new(); config.width],
+            to_play: Player::X,
+            won: None,
+            drawn: false,
+            filled: 0,
+            set_aside: Vec::new(),
+            history: Vec::new(),
+            redo: Vec::new(),
+            turns: Vec::new(),
+            hash: 0,
+            mirror_hash: 0,
+            hashes: Vec::new(),
+        }
+    }
+
+    // Builds a game from a whole board at once; used by conversions and
+    // parsers in the submodules.
+    fn from_columns(
+        config: GameConfig,
+        board: Vec<Vec<Player>>,
+        to_play: Player,
+    ) -> Self {
+        let mut game = Self::with_config(config);
+        for (col, column) in board.iter().enumerate() {
+            for (row, &player) in column.iter().enumerate() {
+                game.toggle_hash(col, row, player);
+            }
+        }
+        game.filled = board.iter().map(|column| column.len()).sum();
+        game.board = board;
+        game.set_to_play(to_play);
+        game.won = game.scan_winner();
+        game
+    }
+
+    pub fn config(&self) -> GameConfig {
+        self.config
+    }
+    pub fn rules(&self) -> Rules {
+        self.rules
+    }
+    pub fn width(&self) -> usize {
+        self.config.width
+    }
+    pub fn height(&self) -> usize {
+        self.config.height
     }
 
+    fn in_range(&self, col: usize, row: usize) -> bool {
+        col < self.width() && row < self.height()
+    }
+
+    // The try_ functions check their arguments and report what was wrong;
+    // the plain versions below are thin wrappers for callers that know
+    // their arguments are fine.
+    pub fn try_get(
+        &self,
+        col: usize,
+        row: usize,
+    ) -> Result<Option<Player>, BoardError> {
+        if col >= self.width() {
+            return Err(BoardError::ColumnOutOfRange(col));
+        }
+        if row >= self.height() {
+            return Err(BoardError::RowOutOfRange(row));
+        }
+        Ok(self.board[col].get(row).cloned())
+        // If you have an Option<&Player> and want an Option<Player>
+        // then .cloned() is useful!
+        // NB: not the same as .clone() !
+    }
+    pub fn try_playable(&self, col: usize) -> Result<bool, BoardError> {
+        if col >= self.width() {
+            return Err(BoardError::ColumnOutOfRange(col));
+        }
+        Ok(self.board[col].len() < self.height())
+    }
+    // Places a piece for player; afterwards it is the other player's turn.
+    // Under the classic rules this only checks that the column has room,
+    // so either player can play. Under the others it's a drop for the
+    // player to move, which try_move in rules.rs checks like any move.
+    pub fn try_play(
+        &mut self,
+        col: usize,
+        player: Player,
+    ) -> Result<(), BoardError> {
+        if self.rules != Rules::Classic {
+            if player != self.to_play {
+                return Err(BoardError::NotYourTurn(player));
+            }
+            return self.try_move(Move::Drop(col));
+        }
+        let playable = self.try_playable(col)?;
+        if self.is_over() {
+            return Err(BoardError::GameOver);
+        }
+        if !playable {
+            return Err(BoardError::ColumnFull(col));
+        }
+        self.place(col, player);
+        // A new play makes the old future unreachable.
+        self.redo.clear();
+        Ok(())
+    }
+
+    // The unchecked parts of playing and taking back a play. These keep
+    // the history and cached fields in step with the board.
+    fn place(&mut self, col: usize, player: Player) {
+        self.hashes.push(self.hash);
+        self.turns.push(self.to_play);
+        self.board[col].push(player);
+        self.toggle_hash(col, self.board[col].len() - 1, player);
+        self.set_to_play(player.other());
+        self.filled += 1;
+        self.history.push((Move::Drop(col), player));
+        // Under Pop 10, lines don't win by themselves: see rules.rs.
+        if self.rules == Rules::Pop10 {
+            self.skip_if_stuck();
+        } else {
+            self.won = self.win_through((col, self.board[col].len() - 1));
+        }
+        self.update_drawn();
+    }
+    fn unplace(&mut self) -> Option<(Move, Player)> {
+        let (play, player) = *self.history.last()?;
+        match play {
+            Move::Drop(col) => {
+                self.history.pop();
+                self.hashes.pop();
+                self.board[col].pop();
+                self.toggle_hash(col, self.board[col].len(), player);
+                self.filled -= 1;
+            }
+            Move::Pop(col) => self.unpop(col, player),
+        }
+        let to_play = self.turns.pop().expect("a turn for every play");
+        self.set_to_play(to_play);
+        // Nobody could play from a finished game, so the position before
+        // any play was still in progress.
+        self.won = None;
+        self.drawn = false;
+        Some((play, player))
+    }
+
+    // XOR is its own inverse, so the same call adds or removes a piece.
+    fn toggle_hash(&mut self, col: usize, row: usize, player: Player) {
+        self.hash ^= zobrist::piece(col, row, player);
+        self.mirror_hash ^= zobrist::piece(self.width() - 1 - col, row, player);
+    }
+    fn set_to_play(&mut self, player: Player) {
+        if player != self.to_play {
+            self.hash ^= zobrist::SIDE_TO_MOVE;
+            self.mirror_hash ^= zobrist::SIDE_TO_MOVE;
+            self.to_play = player;
+        }
+    }
+
+    // The Zobrist hash of the position (not called hash, which would clash
+    // with Hash::hash for anyone with the trait in scope).
+    pub fn zobrist(&self) -> u64 {
+        self.hash
+    }
+    // The hash of the position reflected left to right.
+    pub fn mirror_zobrist(&self) -> u64 {
+        self.mirror_hash
+    }
+    // A position and its mirror image are the same for every purpose except
+    // column numbers, so they share a canonical hash: the smaller of the two.
+    pub fn canonical_zobrist(&self) -> u64 {
+        self.hash.min(self.mirror_hash)
+    }
+    pub fn is_canonical(&self) -> bool {
+        self.hash <= self.mirror_hash
+    }
+
+    // The position reflected left to right (without the history, so a
+    // Pop 10 game forgets any pieces set aside).
+    pub fn mirrored(&self) -> Self {
+        let board = self.board.iter().rev().cloned().collect();
+        let mut game = Self::from_columns(self.config, board, self.to_play);
+        let last = self.width() - 1;
+        game.rules = self.rules;
+        game.drawn = self.drawn;
+        game.won = self.won.as_ref().map(|(player, cells)| {
+            (*player, cells.iter().map(|&(i, j)| (last - i, j)).collect())
+        });
+        game
+    }
+
+    // Takes back the most recent play, returning it. It becomes that
+    // player's turn again.
+    pub fn undo(&mut self) -> Option<(Move, Player)> {
+        let play = self.unplace()?;
+        self.redo.push(play);
+        Some(play)
+    }
+
+    // Plays the most recently undone play again.
+    pub fn redo(&mut self) -> Option<(Move, Player)> {
+        let (play, player) = self.redo.pop()?;
+        match play {
+            Move::Drop(col) => self.place(col, player),
+            Move::Pop(col) => self.pop(col, player),
+        }
+        Some((play, player))
+    }
+
+    // Plays made on this game so far, oldest first. Games built from a
+    // whole board at once (e.g. converted from a BitBoard) start with an
+    // empty history, so only the plays made since can be undone.
+    pub fn history(&self) -> &[(Move, Player)] {
+        &self.history
+    }
+
+    pub fn move_count(&self) -> usize {
+        self.history.len()
+    }
+
+    // Off the board counts as empty.
     pub fn get(&self, col: usize, row: usize) -> Option<Player> {
         // Remember:
         // assert!: called in debug and --release mode
         // debug_assert!: only called in debug mode
-        debug_assert!(Self::in_range(col, row));
-        self.board[col].get(row).cloned()
-        // If you have an Option<&Player> and want an Option<Player>
-        // then .cloned() is useful!
-        // NB: not the same as .clone() !
+        // Here we don't need either: try_get already checked.
+        self.try_get(col, row).unwrap_or(None)
     }
     pub fn playable(&self, col: usize) -> bool {
-        debug_assert!(col <= BOARD_LEN);
-        self.board[col].len() < BOARD_HGT
+        self.try_playable(col) == Ok(true)
     }
     pub fn play(&mut self, col: usize, player: Player) {
-        debug_assert!(col <= BOARD_LEN);
-        debug_assert!(self.playable(col));
-        self.board[col].push(player);
+        // Panicking on purpose is still memory safe, and much better than
+        // silently stacking a sixth piece onto a column.
+        if let Err(err) = self.try_play(col, player) {
+            panic!("illegal play in column {}: {}", col, err);
+        }
+    }
+
+    pub fn to_play(&self) -> Player {
+        self.to_play
+    }
+
+    // Places a piece for whoever's turn it is, then passes the turn.
+    // Result<(), E>: either Ok(()) or Err(e) explaining what went wrong.
+    pub fn make_move(&mut self, col: usize) -> Result<(), BoardError> {
+        let player = self.to_play;
+        self.try_play(col, player)
     }
 
     /*
@@ -87,8 +494,10 @@ impl Game {
     // The way to use iterators: return 'impl Iterator<...> + '_'
     // Don't worry about the '_ for now, but it tells Rust to try
     // to figure out a valid lifetime for the result.
+    // The columns with room for another piece. Under PopOut and Pop 10
+    // not all of these are allowed; see legal_moves in rules.rs.
     pub fn valid_plays(&self) -> impl Iterator<Item = usize> + '_ {
-        (0..BOARD_LEN).filter(move |&i| self.playable(i))
+        (0..self.width()).filter(move |&i| self.playable(i))
         // Doesn't actually call self.playable(i) on any i when the
         // function is called; it only calls self.playable when the
         // function is used.
@@ -102,11 +511,11 @@ impl Game {
         }
     }
 
-    fn cells() -> impl Iterator<Item = (usize, usize)> {
-        // No need to take &self as a parameter if it's not needed.
-        // This would be called like Self::cells()
-        // rather than self.cells()
-        (0..BOARD_LEN).flat_map(|i| (0..BOARD_HGT).map(move |j| (i, j)))
+    fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        // This used to be Self::cells(), with no &self parameter, back when
+        // the board size was a constant. Now the size lives in self.config.
+        let height = self.height();
+        (0..self.width()).flat_map(move |i| (0..height).map(move |j| (i, j)))
         // syntax: move |j| (i, j)
         // This is called a closure. It's like a lambda or anonymous
         // function in other languages.
@@ -116,33 +525,557 @@ impl Game {
         // before the closure as it often solves issues.
     }
 
-    fn blocks_of_four() -> impl Iterator<Item = [(usize, usize); 4]> {
-        let horiz = Self::cells()
-            .map(|(i, j)| [(i, j), (i + 1, j), (i + 2, j), (i + 3, j)]);
-        let vert = Self::cells()
-            .map(|(i, j)| [(i, j), (i, j + 1), (i, j + 2), (i, j + 3)]);
-        let diag1 = Self::cells().map(|(i, j)| {
-            [(i, j), (i + 1, j + 1), (i + 2, j + 2), (i + 3, j + 3)]
-        });
-        let diag2 = Self::cells().map(|(i, j)| {
-            [(i, j + 3), (i + 1, j + 2), (i + 2, j + 1), (i + 3, j)]
-        });
-        horiz
-            .chain(vert)
-            .chain(diag1)
-            .chain(diag2)
-            .filter(|&blck| Self::in_range(blck[0].0, blck[0].1))
-            .filter(|&blck| Self::in_range(blck[3].0, blck[3].1))
+    // Every straight line of config.connect cells that fits on the board.
+    // Each block is a Vec rather than an array like [(usize, usize); 4]
+    // because the length is only known at runtime.
+    // Public so that analysis code can look at the same lines we do.
+    pub fn blocks(&self) -> impl Iterator<Item = Vec<(usize, usize)>> + '_ {
+        // A line of one cell runs in every direction at once; count it once.
+        let directions = if self.config.connect == 1 { 1 } else { 4 };
+        self.cells().flat_map(move |start| {
+            DIRECTIONS[..directions]
+                .iter()
+                .filter_map(move |&dir| self.block(start, dir))
+        })
     }
 
-    pub fn winner(&self) -> Option<Player> {
-        for blck in Self::blocks_of_four() {
+    fn block(
+        &self,
+        start: (usize, usize),
+        dir: (isize, isize),
+    ) -> Option<Vec<(usize, usize)>> {
+        // Collecting an iterator of Options into an Option<Vec<_>> gives
+        // None as soon as any step runs off the board.
+        (0..self.config.connect).map(|k| self.step(start, dir, k)).collect()
+    }
+
+    // The cell k steps away from start in direction dir, if on the board.
+    fn step(
+        &self,
+        (col, row): (usize, usize),
+        (dx, dy): (isize, isize),
+        k: usize,
+    ) -> Option<(usize, usize)> {
+        let col = col as isize + dx * k as isize;
+        let row = row as isize + dy * k as isize;
+        if col < 0 || row < 0 || !self.in_range(col as usize, row as usize) {
+            None
+        } else {
+            Some((col as usize, row as usize))
+        }
+    }
+
+    // The first block filled by a single player, if any.
+    // This checks every block on the board, so we only use it when a
+    // whole board appears at once rather than one play at a time.
+    fn scan_winner(&self) -> Option<(Player, Vec<(usize, usize)>)> {
+        for blck in self.blocks() {
             for &player in &[Player::X, Player::O] {
                 if blck.iter().all(|&(i, j)| self.get(i, j) == Some(player)) {
-                    return Some(player);
+                    return Some((player, blck));
                 }
             }
         }
         None
     }
+
+    // A winning block through the piece at (col, row), if there is one.
+    // Any line that wasn't already there must pass through the newest
+    // piece, so after a play this is the only place we need to look.
+    fn win_through(
+        &self,
+        (col, row): (usize, usize),
+    ) -> Option<(Player, Vec<(usize, usize)>)> {
+        // ? works on Options too: return None early if the cell is empty.
+        let player = self.get(col, row)?;
+        for &(dx, dy) in DIRECTIONS.iter() {
+            // Walk backwards to the start of this player's run...
+            let mut start = (col, row);
+            while let Some((i, j)) = self.step(start, (-dx, -dy), 1) {
+                if self.get(i, j) != Some(player) {
+                    break;
+                }
+                start = (i, j);
+            }
+            // ...then see if the run is long enough.
+            if let Some(blck) = self.block(start, (dx, dy)) {
+                if blck.iter().all(|&(i, j)| self.get(i, j) == Some(player)) {
+                    return Some((player, blck));
+                }
+            }
+        }
+        None
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        self.won.as_ref().map(|&(player, _)| player)
+    }
+
+    fn is_full(&self) -> bool {
+        self.filled == self.width() * self.height()
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if let Some((player, ref cells)) = self.won {
+            GameStatus::Won { player, cells: cells.clone() }
+        } else if self.is_over() {
+            GameStatus::Draw
+        } else {
+            GameStatus::InProgress { to_play: self.to_play }
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        if self.won.is_some() {
+            return true;
+        }
+        match self.rules {
+            Rules::Classic => self.is_full(),
+            _ => self.drawn,
+        }
+    }
+}
+
+#[test]
+fn test_make_move_alternates() {
+    let mut game = Game::new();
+    assert_eq!(game.to_play(), Player::X);
+    game.make_move(3).unwrap();
+    assert_eq!(game.to_play(), Player::O);
+    game.make_move(3).unwrap();
+    assert_eq!(game.to_play(), Player::X);
+    assert_eq!(game.get(3, 0), Some(Player::X));
+    assert_eq!(game.get(3, 1), Some(Player::O));
+}
+
+#[test]
+fn test_make_move_errors() {
+    let mut game = Game::new();
+    assert_eq!(
+        game.make_move(BOARD_LEN),
+        Err(BoardError::ColumnOutOfRange(BOARD_LEN))
+    );
+    for _ in 0..BOARD_HGT {
+        game.make_move(0).unwrap();
+    }
+    assert_eq!(game.make_move(0), Err(BoardError::ColumnFull(0)));
+    // Rejected moves don't use up a turn.
+    assert_eq!(game.to_play(), Player::O);
+}
+
+#[test]
+fn test_make_move_after_win() {
+    let mut game = Game::new();
+    for col in &[0, 0, 1, 1, 2, 2, 3] {
+        game.make_move(*col).unwrap();
+    }
+    assert_eq!(game.winner(), Some(Player::X));
+    assert_eq!(game.make_move(5), Err(BoardError::GameOver));
+}
+
+#[test]
+fn test_try_get_bounds() {
+    let mut game = Game::new();
+    game.play(BOARD_LEN - 1, Player::X);
+    assert_eq!(game.try_get(BOARD_LEN - 1, 0), Ok(Some(Player::X)));
+    assert_eq!(game.try_get(BOARD_LEN - 1, BOARD_HGT - 1), Ok(None));
+    assert_eq!(
+        game.try_get(BOARD_LEN, 0),
+        Err(BoardError::ColumnOutOfRange(BOARD_LEN))
+    );
+    assert_eq!(
+        game.try_get(0, BOARD_HGT),
+        Err(BoardError::RowOutOfRange(BOARD_HGT))
+    );
+    assert_eq!(game.get(BOARD_LEN, BOARD_HGT), None);
+    assert!(!game.playable(BOARD_LEN));
+}
+
+#[test]
+fn test_try_play_errors() {
+    let mut game = Game::new();
+    assert_eq!(
+        game.try_play(10, Player::X),
+        Err(BoardError::ColumnOutOfRange(10))
+    );
+    for col in &[0, 0, 1, 1, 2, 2] {
+        game.make_move(*col).unwrap();
+    }
+    game.try_play(3, Player::X).unwrap();
+    assert_eq!(game.try_play(4, Player::O), Err(BoardError::GameOver));
+}
+
+#[test]
+#[should_panic(expected = "illegal play in column 10")]
+fn test_play_out_of_range_panics() {
+    Game::new().play(10, Player::X);
+}
+
+#[test]
+fn test_columns_never_overflow() {
+    let mut game = Game::new();
+    for col in 0..BOARD_LEN {
+        for _ in 0..2 * BOARD_HGT {
+            let _ = game.make_move(col);
+            assert!(game.board[col].len() <= BOARD_HGT);
+        }
+    }
+    for col in 0..BOARD_LEN {
+        assert!(game.board[col].len() <= BOARD_HGT);
+        assert!(game.try_play(col, Player::X).is_err());
+    }
+}
+
+#[test]
+fn test_win_on_right_edge() {
+    // The last column used to be treated as in range of a block of four
+    // one past the edge of the board.
+    let mut game = Game::new();
+    for col in &[7, 7, 8, 8, 9, 9] {
+        game.make_move(*col).unwrap();
+    }
+    assert_eq!(game.winner(), None);
+    game.make_move(6).unwrap();
+    assert_eq!(game.winner(), Some(Player::X));
+}
+
+#[test]
+fn test_config_sizes() {
+    for &(width, height) in &[(7, 6), (8, 7), (9, 7), (4, 4)] {
+        let mut game = Game::with_config(GameConfig::new(width, height));
+        assert_eq!(game.valid_plays().count(), width);
+        assert_eq!(game.cells().count(), width * height);
+        assert_eq!(
+            game.try_play(width, Player::X),
+            Err(BoardError::ColumnOutOfRange(width))
+        );
+        for _ in 0..height {
+            game.make_move(width - 1).unwrap();
+        }
+        assert_eq!(
+            game.make_move(width - 1),
+            Err(BoardError::ColumnFull(width - 1))
+        );
+        assert_eq!(game.valid_plays().count(), width - 1);
+    }
+}
+
+#[test]
+fn test_blocks_count() {
+    // A w x h board has (w-n+1)h horizontal, w(h-n+1) vertical and
+    // 2(w-n+1)(h-n+1) diagonal blocks of n.
+    for &(w, h) in &[(7, 6), (8, 7), (9, 7), (10, 5), (5, 5)] {
+        for n in 3..6 {
+            let config = GameConfig { connect: n, ..GameConfig::new(w, h) };
+            let game = Game::with_config(config);
+            let expected = (w - n + 1) * h
+                + w * (h - n + 1)
+                + 2 * (w - n + 1) * (h - n + 1);
+            assert_eq!(game.blocks().count(), expected);
+            assert!(game.blocks().all(|blck| blck.len() == n));
+        }
+        // One in a row: every cell is a block, once.
+        let config = GameConfig { connect: 1, ..GameConfig::new(w, h) };
+        assert_eq!(Game::with_config(config).blocks().count(), w * h);
+    }
+}
+
+#[test]
+fn test_connect_n_winner() {
+    // Whoever plays first along the bottom row wins at move 2n - 1.
+    for &(w, h) in &[(7, 6), (8, 7), (9, 7), (10, 5)] {
+        for n in 3..6 {
+            let config = GameConfig { connect: n, ..GameConfig::new(w, h) };
+            let mut game = Game::with_config(config);
+            for col in 0..n - 1 {
+                game.make_move(col).unwrap();
+                game.make_move(col).unwrap();
+            }
+            assert_eq!(game.winner(), None);
+            game.make_move(n - 1).unwrap();
+            assert_eq!(game.winner(), Some(Player::X));
+        }
+    }
+}
+
+#[test]
+fn test_connect_n_diagonal() {
+    for &n in &[3, 5] {
+        let config = GameConfig { connect: n, ..GameConfig::new(9, 7) };
+        let mut game = Game::with_config(config);
+        // Staircase of O's under an X diagonal going down and to the right.
+        for col in 0..n {
+            for _ in 0..n - 1 - col {
+                game.play(col, Player::O);
+            }
+        }
+        for col in 1..n {
+            game.play(col, Player::X);
+        }
+        assert_eq!(game.winner(), None);
+        game.play(0, Player::X);
+        assert_eq!(game.winner(), Some(Player::X));
+    }
+}
+
+#[test]
+fn test_config_winner() {
+    // Vertical win in the top-right corner of a tall narrow board.
+    let mut game = Game::with_config(GameConfig::new(4, 7));
+    for _ in 0..3 {
+        game.play(3, Player::X);
+    }
+    for _ in 0..3 {
+        game.play(3, Player::O);
+    }
+    assert_eq!(game.winner(), None);
+    game.play(3, Player::O);
+    assert_eq!(game.get(3, 6), Some(Player::O));
+    assert_eq!(game.winner(), Some(Player::O));
+}
+
+#[test]
+fn test_status_won() {
+    let mut game = Game::new();
+    assert_eq!(game.status(), GameStatus::InProgress { to_play: Player::X });
+    for col in &[2, 2, 3, 3, 4, 4] {
+        game.make_move(*col).unwrap();
+    }
+    assert_eq!(game.status(), GameStatus::InProgress { to_play: Player::X });
+    game.make_move(5).unwrap();
+    match game.status() {
+        GameStatus::Won { player, mut cells } => {
+            assert_eq!(player, Player::X);
+            cells.sort();
+            assert_eq!(cells, vec![(2, 0), (3, 0), (4, 0), (5, 0)]);
+        }
+        status => panic!("expected a win, got {:?}", status),
+    }
+    assert!(game.is_over());
+}
+
+#[test]
+fn test_status_draw() {
+    // Rows alternating XXOO and OOXX never line up four of a kind.
+    let mut game = Game::with_config(GameConfig::new(4, 4));
+    for row in 0..4 {
+        for col in 0..4 {
+            let player =
+                if (row + col / 2) % 2 == 0 { Player::X } else { Player::O };
+            game.play(col, player);
+        }
+    }
+    assert_eq!(game.winner(), None);
+    assert_eq!(game.status(), GameStatus::Draw);
+    assert!(game.is_over());
+    assert_eq!(game.make_move(0), Err(BoardError::GameOver));
+}
+
+// The position after playing moves (columns) from an empty board, for
+// the tests in every module.
+#[cfg(test)]
+fn test_position(config: GameConfig, moves: &[usize]) -> Game {
+    let mut game = Game::with_config(config);
+    for &col in moves {
+        game.make_move(col).unwrap();
+    }
+    game
+}
+
+// Small xorshift generator so tests can play lots of pseudo-random games.
+#[cfg(test)]
+fn test_random_games(seed: u64, mut each: impl FnMut(&Game)) {
+    let mut state = seed;
+    for &config in &[
+        GameConfig::default(),
+        GameConfig::standard(),
+        GameConfig { connect: 3, ..GameConfig::new(5, 4) },
+        GameConfig { connect: 5, ..GameConfig::new(9, 7) },
+    ] {
+        for _ in 0..50 {
+            let mut game = Game::with_config(config);
+            each(&game);
+            while !game.is_over() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                let plays: Vec<usize> = game.valid_plays().collect();
+                game.make_move(plays[state as usize % plays.len()]).unwrap();
+                each(&game);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_incremental_matches_full_scan() {
+    test_random_games(0x5eed, |game| {
+        let scanned = game.scan_winner();
+        assert_eq!(game.winner(), scanned.as_ref().map(|&(p, _)| p));
+        if let GameStatus::Won { player, cells } = game.status() {
+            // Both should report a genuine winning line, but not
+            // necessarily the same one.
+            assert_eq!(cells.len(), game.config().connect);
+            assert!(cells.iter().all(|&(i, j)| game.get(i, j) == Some(player)));
+        }
+        let full = game.valid_plays().next().is_none();
+        assert_eq!(
+            game.status() == GameStatus::Draw,
+            full && scanned.is_none()
+        );
+    });
+}
+
+#[test]
+fn test_undo_redo() {
+    let mut game = Game::new();
+    let start = game.clone();
+    for col in &[3, 4, 3] {
+        game.make_move(*col).unwrap();
+    }
+    let after = game.clone();
+    assert_eq!(game.move_count(), 3);
+    assert_eq!(
+        game.history(),
+        &[
+            (Move::Drop(3), Player::X),
+            (Move::Drop(4), Player::O),
+            (Move::Drop(3), Player::X)
+        ]
+    );
+
+    assert_eq!(game.undo(), Some((Move::Drop(3), Player::X)));
+    assert_eq!(game.to_play(), Player::X);
+    assert_eq!(game.get(3, 1), None);
+    assert_eq!(game.undo(), Some((Move::Drop(4), Player::O)));
+    assert_eq!(game.undo(), Some((Move::Drop(3), Player::X)));
+    assert_eq!(game.undo(), None);
+    assert_eq!(game, start);
+    assert_eq!(game.move_count(), 0);
+
+    assert_eq!(game.redo(), Some((Move::Drop(3), Player::X)));
+    assert_eq!(game.redo(), Some((Move::Drop(4), Player::O)));
+    assert_eq!(game.redo(), Some((Move::Drop(3), Player::X)));
+    assert_eq!(game.redo(), None);
+    assert_eq!(game, after);
+    assert_eq!(game.history(), after.history());
+}
+
+#[test]
+fn test_new_play_clears_redo() {
+    let mut game = Game::new();
+    game.make_move(0).unwrap();
+    game.make_move(1).unwrap();
+    game.undo();
+    game.make_move(2).unwrap();
+    assert_eq!(game.redo(), None);
+    assert_eq!(
+        game.history(),
+        &[(Move::Drop(0), Player::X), (Move::Drop(2), Player::O)]
+    );
+}
+
+#[test]
+fn test_undo_win() {
+    let mut game = Game::new();
+    for col in &[0, 0, 1, 1, 2, 2, 3] {
+        game.make_move(*col).unwrap();
+    }
+    assert!(game.is_over());
+    game.undo();
+    assert_eq!(game.status(), GameStatus::InProgress { to_play: Player::X });
+    game.make_move(4).unwrap();
+    assert_eq!(game.winner(), None);
+    game.redo();
+    assert_eq!(game.winner(), None);
+}
+
+#[test]
+fn test_undo_out_of_turn() {
+    // O moving first isn't X's turn being skipped for good: undo puts the
+    // turn back with X, so the game and its hash are as they started.
+    let mut game = Game::new();
+    game.play(0, Player::O);
+    assert_eq!(game.to_play(), Player::X);
+    game.play(1, Player::O);
+    game.undo();
+    assert_eq!(game.to_play(), Player::X);
+    game.undo();
+    assert_eq!(game, Game::new());
+    assert_eq!(game.zobrist(), Game::new().zobrist());
+    game.redo();
+    game.redo();
+    assert_eq!(game.get(1, 0), Some(Player::O));
+    assert_eq!(game.to_play(), Player::X);
+}
+
+#[test]
+fn test_undo_restores_every_position() {
+    test_random_games(0x0dd, |game| {
+        if !game.is_over() {
+            return;
+        }
+        let mut game = game.clone();
+        let mut seen = vec![game.clone()];
+        while game.undo().is_some() {
+            assert_eq!(game.winner(), game.scan_winner().map(|(p, _)| p));
+            seen.push(game.clone());
+        }
+        assert_eq!(game, Game::with_config(game.config()));
+        while game.redo().is_some() {
+            seen.pop();
+            assert_eq!(Some(&game), seen.last());
+        }
+    });
+}
+
+#[test]
+fn test_hash_incremental() {
+    test_random_games(0x4a5, |game| {
+        let rebuilt =
+            Game::from_columns(game.config, game.board.clone(), game.to_play);
+        assert_eq!(game.zobrist(), rebuilt.zobrist());
+        assert_eq!(game.mirror_zobrist(), rebuilt.mirror_zobrist());
+        let mirror = game.mirrored();
+        assert_eq!(mirror.zobrist(), game.mirror_zobrist());
+        assert_eq!(mirror.mirror_zobrist(), game.zobrist());
+        assert_eq!(mirror.canonical_zobrist(), game.canonical_zobrist());
+    });
+}
+
+#[test]
+fn test_hash_transpositions() {
+    let mut game1 = Game::new();
+    let mut game2 = Game::new();
+    for col in &[2, 3, 4, 5] {
+        game1.make_move(*col).unwrap();
+    }
+    for col in &[4, 5, 2, 3] {
+        game2.make_move(*col).unwrap();
+    }
+    assert_eq!(game1, game2);
+    assert_eq!(game1.zobrist(), game2.zobrist());
+    game2.undo();
+    assert_ne!(game1.zobrist(), game2.zobrist());
+    game2.redo();
+    assert_eq!(game1.zobrist(), game2.zobrist());
+    // Same pieces, different player to move.
+    let mut game3 = game1.clone();
+    game3.set_to_play(Player::O);
+    assert_ne!(game1.zobrist(), game3.zobrist());
+}
+
+#[test]
+fn test_hash_symmetric() {
+    let mut game = Game::with_config(GameConfig::standard());
+    assert!(game.is_canonical());
+    game.make_move(3).unwrap();
+    assert_eq!(game.zobrist(), game.mirror_zobrist());
+    game.make_move(0).unwrap();
+    let mut other = Game::with_config(GameConfig::standard());
+    other.make_move(3).unwrap();
+    other.make_move(6).unwrap();
+    assert_ne!(game.zobrist(), other.zobrist());
+    assert_eq!(game.canonical_zobrist(), other.canonical_zobrist());
+    assert_eq!(game.mirrored(), other);
 }
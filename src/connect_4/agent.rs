@@ -0,0 +1,272 @@
+/*
+    Agents: whatever chooses the moves for one side of a game.
+
+    Player is just a color. An Agent is anything that can look at a Game
+    and pick a column: a random mover, a search engine, a human at the
+    keyboard... Trait objects (&mut dyn Agent) let the match runner play
+    any two of them against each other without knowing which kinds they are.
+
+    The agents here only play the classic rules (see rules.rs). Given a
+    game played by others, they choose a column off the board, which
+    forfeits it, rather than play it as if it were classic.
+*/
+
+use std::rc::Rc;
+
+use super::book::Book;
+use super::engine::Engine;
+use super::mcts::{Budget, Mcts};
+use super::rng::Rng;
+use super::{Game, GameConfig, Player};
+
+pub trait Agent {
+    fn name(&self) -> String;
+
+    // Only called on games that aren't over yet. Returning a column that
+    // can't be played forfeits the game.
+    fn choose(&mut self, game: &Game) -> usize;
+
+    // Agents that use randomness restart their RNG from seed, so that a
+    // series of games can be replayed exactly. Default: nothing to do.
+    fn reseed(&mut self, _seed: u64) {}
+}
+
+// Picks uniformly among the legal moves.
+#[derive(Clone, Debug)]
+pub struct RandomAgent {
+    rng: Rng,
+}
+
+impl RandomAgent {
+    pub fn new(seed: u64) -> Self {
+        Self { rng: Rng::new(seed) }
+    }
+}
+
+impl Agent for RandomAgent {
+    fn name(&self) -> String {
+        "random".to_owned()
+    }
+    fn choose(&mut self, game: &Game) -> usize {
+        if game.require_classic().is_err() {
+            return forfeit(game);
+        }
+        let plays: Vec<usize> = game.valid_plays().collect();
+        self.rng.choose(&plays).expect("no legal moves")
+    }
+    fn reseed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+}
+
+// Always plays the leftmost column that isn't full.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct FirstLegalAgent;
+
+impl Agent for FirstLegalAgent {
+    fn name(&self) -> String {
+        "first-legal".to_owned()
+    }
+    fn choose(&mut self, game: &Game) -> usize {
+        if game.require_classic().is_err() {
+            return forfeit(game);
+        }
+        game.valid_plays().next().expect("no legal moves")
+    }
+}
+
+// Alpha-beta search to a fixed depth.
+#[derive(Clone, Debug)]
+pub struct SearchAgent {
+    engine: Engine,
+}
+
+impl SearchAgent {
+    pub fn new(depth: usize) -> Self {
+        Self { engine: Engine::new(depth) }
+    }
+}
+
+impl Agent for SearchAgent {
+    fn name(&self) -> String {
+        format!("search-{}", self.engine.depth)
+    }
+    fn choose(&mut self, game: &Game) -> usize {
+        match self.engine.search(game) {
+            Ok(result) => result.best.expect("no legal moves"),
+            Err(_) => forfeit(game),
+        }
+    }
+}
+
+// Plays from an opening book while the game is in it, then searches.
+// Rc, so that several agents can share one book without copying it.
+#[derive(Clone, Debug)]
+pub struct BookAgent {
+    book: Rc<Book>,
+    engine: Engine,
+}
+
+impl BookAgent {
+    pub fn new(book: Rc<Book>, depth: usize) -> Self {
+        Self { book, engine: Engine::new(depth) }
+    }
+}
+
+impl Agent for BookAgent {
+    fn name(&self) -> String {
+        format!("book+search-{}", self.engine.depth)
+    }
+    fn choose(&mut self, game: &Game) -> usize {
+        match self.book.search(game, &mut self.engine) {
+            Ok(result) => result.best.expect("no legal moves"),
+            Err(_) => forfeit(game),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MctsAgent {
+    mcts: Mcts,
+}
+
+impl MctsAgent {
+    pub fn new(budget: Budget, seed: u64) -> Self {
+        Self { mcts: Mcts::new(budget, seed) }
+    }
+}
+
+impl Agent for MctsAgent {
+    fn name(&self) -> String {
+        match self.mcts.budget {
+            Budget::Iterations(n) => format!("mcts-{}", n),
+            Budget::Time(limit) => format!("mcts-{}ms", limit.as_millis()),
+        }
+    }
+    fn choose(&mut self, game: &Game) -> usize {
+        match self.mcts.choose(game) {
+            Ok(best) => best.expect("no legal moves"),
+            Err(_) => forfeit(game),
+        }
+    }
+    fn reseed(&mut self, seed: u64) {
+        self.mcts.reseed(seed);
+    }
+}
+
+// What the agents here choose in a game they can't play: a column off the
+// board, so that it's a forfeit.
+fn forfeit(game: &Game) -> usize {
+    game.width()
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MatchOutcome {
+    Won(Player),
+    Draw,
+    // player tried to play col, which wasn't a legal move, and so lost.
+    Forfeit { player: Player, col: usize },
+}
+
+impl MatchOutcome {
+    pub fn winner(&self) -> Option<Player> {
+        match *self {
+            MatchOutcome::Won(player) => Some(player),
+            MatchOutcome::Draw => None,
+            MatchOutcome::Forfeit { player, .. } => Some(player.other()),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MatchResult {
+    pub game: Game,
+    pub moves: Vec<usize>,
+    pub outcome: MatchOutcome,
+}
+
+// Plays one game to the end; x moves first.
+pub fn play_match(
+    x: &mut dyn Agent,
+    o: &mut dyn Agent,
+    config: GameConfig,
+) -> MatchResult {
+    let mut game = Game::with_config(config);
+    let mut moves = Vec::new();
+    while !game.is_over() {
+        let player = game.to_play();
+        let col = match player {
+            Player::X => x.choose(&game),
+            Player::O => o.choose(&game),
+        };
+        if game.make_move(col).is_err() {
+            let outcome = MatchOutcome::Forfeit { player, col };
+            return MatchResult { game, moves, outcome };
+        }
+        moves.push(col);
+    }
+    let outcome = match game.winner() {
+        Some(player) => MatchOutcome::Won(player),
+        None => MatchOutcome::Draw,
+    };
+    MatchResult { game, moves, outcome }
+}
+
+#[test]
+fn test_first_legal_vs_first_legal() {
+    let config = GameConfig::standard();
+    let result = play_match(&mut FirstLegalAgent, &mut FirstLegalAgent, config);
+    assert_eq!(result.moves.len(), result.game.move_count());
+    assert_eq!(result.outcome.winner(), result.game.winner());
+    assert!(result.game.is_over());
+    let mut replay = Game::with_config(config);
+    for &col in &result.moves {
+        replay.make_move(col).unwrap();
+    }
+    assert_eq!(replay, result.game);
+}
+
+#[test]
+fn test_search_beats_random() {
+    for seed in 0..3 {
+        let mut search = SearchAgent::new(4);
+        let mut random = RandomAgent::new(seed);
+        let config = GameConfig::standard();
+        let result = play_match(&mut search, &mut random, config);
+        assert_eq!(result.outcome, MatchOutcome::Won(Player::X));
+        let result = play_match(&mut random, &mut search, config);
+        assert_eq!(result.outcome, MatchOutcome::Won(Player::O));
+    }
+}
+
+#[test]
+fn test_random_reproducible() {
+    let config = GameConfig::default();
+    let first =
+        play_match(&mut RandomAgent::new(1), &mut RandomAgent::new(2), config);
+    let second =
+        play_match(&mut RandomAgent::new(1), &mut RandomAgent::new(2), config);
+    assert_eq!(first.moves, second.moves);
+    assert_eq!(first.outcome, second.outcome);
+}
+
+#[test]
+fn test_forfeit() {
+    struct Stubborn;
+    impl Agent for Stubborn {
+        fn name(&self) -> String {
+            "stubborn".to_owned()
+        }
+        fn choose(&mut self, _game: &Game) -> usize {
+            0
+        }
+    }
+    let config = GameConfig::new(4, 2);
+    let result = play_match(&mut Stubborn, &mut Stubborn, config);
+    assert_eq!(result.moves, vec![0, 0]);
+    assert_eq!(
+        result.outcome,
+        MatchOutcome::Forfeit { player: Player::X, col: 0 }
+    );
+    assert_eq!(result.outcome.winner(), Some(Player::O));
+}
@@ -0,0 +1,256 @@
+/*
+    Threats and hints: the things a coach would point out.
+
+    A threat is a line (one of the blocks from Game::blocks) where one
+    player has every cell but one, and that last cell is empty. If the
+    empty cell is also the next one to fill in its column, the player can
+    win right now by playing there; otherwise the threat is waiting for the
+    column underneath to fill up.
+
+    All of this assumes the classic rules, where pieces only ever go in
+    at the top, so games played by others (see rules.rs) are turned down.
+*/
+
+use std::fmt;
+
+use super::rules::ClassicOnly;
+#[cfg(test)]
+use super::{test_position, GameConfig};
+use super::{Game, Player};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Threat {
+    pub player: Player,
+    // The empty cell that would complete the line.
+    pub cell: (usize, usize),
+    pub block: Vec<(usize, usize)>,
+    // Whether the cell can be played into on the next move.
+    pub playable: bool,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Hint {
+    // You can win now by playing in this column.
+    Win(usize),
+    // Your opponent would win in this column next move.
+    Block(usize),
+    // Your opponent can win in more than one column; you can only block one.
+    Outnumbered(Vec<usize>),
+    // Playing in any of these columns lets your opponent win.
+    Avoid(Vec<usize>),
+}
+
+// The columns in hints are numbered from 1, as on screen.
+fn columns(cols: &[usize]) -> String {
+    let names: Vec<String> =
+        cols.iter().map(|col| (col + 1).to_string()).collect();
+    names.join(", ")
+}
+
+impl fmt::Display for Hint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Hint::Win(col) => {
+                write!(f, "you can win now in column {}", col + 1)
+            }
+            Hint::Block(col) => write!(f, "you must block column {}", col + 1),
+            Hint::Outnumbered(cols) => write!(
+                f,
+                "your opponent can win in columns {}; you can only block one",
+                columns(cols)
+            ),
+            Hint::Avoid(cols) => write!(
+                f,
+                "don't play in column(s) {}: your opponent would win",
+                columns(cols)
+            ),
+        }
+    }
+}
+
+impl Game {
+    // Every line that needs just one more piece, for either player.
+    pub fn threats(&self) -> Result<Vec<Threat>, ClassicOnly> {
+        self.require_classic()?;
+        let mut threats = Vec::new();
+        for block in self.blocks() {
+            let empty: Vec<(usize, usize)> = block
+                .iter()
+                .cloned()
+                .filter(|&(i, j)| self.get(i, j).is_none())
+                .collect();
+            if empty.len() != 1 {
+                continue;
+            }
+            let cell = empty[0];
+            // Columns fill from the bottom, so the next free cell in
+            // column i is at the height of the column.
+            let playable = self.board[cell.0].len() == cell.1;
+            let owners: Vec<Player> =
+                block.iter().filter_map(|&(i, j)| self.get(i, j)).collect();
+            // With one in a row, a block is a single empty cell: it's a
+            // threat for both players.
+            let players = match owners.first() {
+                Some(&player) => vec![player],
+                None => vec![Player::X, Player::O],
+            };
+            for player in players {
+                if owners.iter().all(|&owner| owner == player) {
+                    let block = block.clone();
+                    threats.push(Threat { player, cell, block, playable });
+                }
+            }
+        }
+        Ok(threats)
+    }
+
+    // The columns where player would win by playing next, in order.
+    pub fn winning_moves(
+        &self,
+        player: Player,
+    ) -> Result<Vec<usize>, ClassicOnly> {
+        let threats = self.threats()?;
+        if self.is_over() {
+            return Ok(Vec::new());
+        }
+        let mut cols: Vec<usize> = threats
+            .into_iter()
+            .filter(|threat| threat.player == player && threat.playable)
+            .map(|threat| threat.cell.0)
+            .collect();
+        cols.sort_unstable();
+        cols.dedup();
+        Ok(cols)
+    }
+
+    // The moves for the player to move after which the opponent can win
+    // straight away.
+    pub fn losing_moves(&self) -> Result<Vec<usize>, ClassicOnly> {
+        self.require_classic()?;
+        let opponent = self.to_play().other();
+        let mut cols = Vec::new();
+        for col in self.valid_plays() {
+            let mut next = self.clone();
+            next.make_move(col).unwrap();
+            if !next.winning_moves(opponent)?.is_empty() {
+                cols.push(col);
+            }
+        }
+        Ok(cols)
+    }
+
+    // The most urgent thing to tell the player to move, if anything.
+    pub fn hint(&self) -> Result<Option<Hint>, ClassicOnly> {
+        self.require_classic()?;
+        if self.is_over() {
+            return Ok(None);
+        }
+        let player = self.to_play();
+        if let Some(&col) = self.winning_moves(player)?.first() {
+            return Ok(Some(Hint::Win(col)));
+        }
+        let against = self.winning_moves(player.other())?;
+        match against.len() {
+            0 => {}
+            1 => return Ok(Some(Hint::Block(against[0]))),
+            _ => return Ok(Some(Hint::Outnumbered(against))),
+        }
+        let losing = self.losing_moves()?;
+        Ok(if losing.is_empty() { None } else { Some(Hint::Avoid(losing)) })
+    }
+}
+
+#[test]
+fn test_threats() {
+    // X has three along the bottom, open at both ends; O has nothing.
+    let game = test_position(GameConfig::standard(), &[1, 1, 2, 2, 3]);
+    let threats = game.threats().unwrap();
+    assert!(threats.iter().all(|threat| threat.player == Player::X));
+    let cells: Vec<(usize, usize)> =
+        threats.iter().map(|threat| threat.cell).collect();
+    assert_eq!(cells, vec![(0, 0), (4, 0)]);
+    assert!(threats.iter().all(|threat| threat.playable));
+    assert_eq!(threats[0].block, vec![(0, 0), (1, 0), (2, 0), (3, 0)]);
+}
+
+#[test]
+fn test_threat_not_yet_playable() {
+    // X's diagonal (0,0) (1,1) (2,2) needs (3,3), three pieces up.
+    let game = test_position(GameConfig::standard(), &[0, 1, 1, 2, 2, 3, 2]);
+    let threat = game
+        .threats()
+        .unwrap()
+        .into_iter()
+        .find(|threat| threat.cell == (3, 3))
+        .unwrap();
+    assert_eq!(threat.player, Player::X);
+    assert!(!threat.playable);
+    assert!(!game.winning_moves(Player::X).unwrap().contains(&3));
+}
+
+#[test]
+fn test_winning_moves_match_brute_force() {
+    super::test_random_games(0x18, |game| {
+        for &player in &[Player::X, Player::O] {
+            let brute: Vec<usize> = if game.is_over() {
+                Vec::new()
+            } else {
+                game.valid_plays()
+                    .filter(|&col| {
+                        let mut next = game.clone();
+                        next.play(col, player);
+                        next.winner() == Some(player)
+                    })
+                    .collect()
+            };
+            assert_eq!(game.winning_moves(player).unwrap(), brute);
+        }
+    });
+}
+
+#[test]
+fn test_hints() {
+    let game = test_position(GameConfig::standard(), &[1, 1, 2, 2, 3]);
+    assert_eq!(game.hint().unwrap(), Some(Hint::Outnumbered(vec![0, 4])));
+    let game = test_position(GameConfig::standard(), &[1, 1, 2, 2, 3, 3]);
+    assert_eq!(game.hint().unwrap(), Some(Hint::Win(0)));
+    assert_eq!(
+        game.hint().unwrap().unwrap().to_string(),
+        "you can win now in column 1"
+    );
+    let game = test_position(GameConfig::standard(), &[0, 6, 0, 6, 0]);
+    assert_eq!(game.hint().unwrap(), Some(Hint::Block(0)));
+    assert_eq!(
+        game.hint().unwrap().unwrap().to_string(),
+        "you must block column 1"
+    );
+    assert_eq!(Game::with_config(GameConfig::standard()).hint().unwrap(), None);
+}
+
+#[test]
+fn test_losing_moves() {
+    // X has (0,0) (1,1) (2,2) and has just blocked O's three along the
+    // bottom. If O plays column 3, X completes the diagonal at (3,3).
+    let game =
+        test_position(GameConfig::standard(), &[0, 1, 1, 2, 2, 3, 2, 3, 4]);
+    assert_eq!(game.losing_moves().unwrap(), vec![3]);
+    assert_eq!(game.hint().unwrap(), Some(Hint::Avoid(vec![3])));
+    assert_eq!(
+        game.hint().unwrap().unwrap().to_string(),
+        "don't play in column(s) 4: your opponent would win"
+    );
+}
+
+#[test]
+fn test_connect_1_threats() {
+    // Every empty cell is a line of one waiting to happen, for both sides.
+    let config = GameConfig { connect: 1, ..GameConfig::new(4, 4) };
+    let game = Game::with_config(config);
+    assert_eq!(game.threats().unwrap().len(), 2 * 16);
+    assert_eq!(game.winning_moves(Player::O).unwrap(), vec![0, 1, 2, 3]);
+    assert_eq!(game.hint().unwrap(), Some(Hint::Win(0)));
+    // After the first piece the game is over: nothing to win.
+    let game = test_position(config, &[2]);
+    assert_eq!(game.winning_moves(Player::O).unwrap(), Vec::<usize>::new());
+    assert_eq!(game.hint().unwrap(), None);
+}
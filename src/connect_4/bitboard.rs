@@ -0,0 +1,342 @@
+/*
+    A second representation of a Connect 4 position: bitboards.
+
+    Instead of a Vec per column, each player's pieces are the 1 bits of a
+    single u128. Cloning is a copy, equality and hashing are a couple of
+    integer comparisons, and checking for a win is a handful of shifts and
+    ANDs. The public functions mirror Game's, so search code can switch
+    between the two without changing its callers.
+
+    A bitboard holds nothing but the pieces and whose turn it is, so it
+    only plays the classic rules: the pieces set aside at Pop 10, or the
+    positions a PopOut game has been through, have nowhere to go.
+*/
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+
+use super::rules::ClassicOnly;
+use super::{BoardError, Game, GameConfig, Player};
+
+// Cell (col, row) is bit col * (height + 1) + row. The extra bit on top of
+// each column is always 0, which stops lines from wrapping around from one
+// column into the next when we shift.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct BitBoard {
+    config: GameConfig,
+    x: u128,
+    o: u128,
+    to_play: Player,
+    winner: Option<Player>,
+}
+
+// Returned when the board doesn't fit in 128 bits.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TooLarge(pub GameConfig);
+
+impl fmt::Display for TooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} board doesn't fit in a bitboard",
+            self.0.width, self.0.height
+        )
+    }
+}
+
+impl error::Error for TooLarge {}
+
+// Why a Game can't be turned into a BitBoard.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ConvertError {
+    TooLarge(TooLarge),
+    ClassicOnly(ClassicOnly),
+}
+
+impl fmt::Display for ConvertError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConvertError::TooLarge(error) => error.fmt(f),
+            ConvertError::ClassicOnly(error) => error.fmt(f),
+        }
+    }
+}
+
+impl error::Error for ConvertError {}
+
+// So that ? can turn either error into a ConvertError.
+impl From<TooLarge> for ConvertError {
+    fn from(error: TooLarge) -> Self {
+        ConvertError::TooLarge(error)
+    }
+}
+
+impl From<ClassicOnly> for ConvertError {
+    fn from(error: ClassicOnly) -> Self {
+        ConvertError::ClassicOnly(error)
+    }
+}
+
+impl BitBoard {
+    pub fn fits(config: GameConfig) -> bool {
+        config.width * (config.height + 1) <= 128
+    }
+
+    pub fn new(config: GameConfig) -> Result<Self, TooLarge> {
+        if !Self::fits(config) {
+            return Err(TooLarge(config));
+        }
+        assert!(config.width > 0 && config.height > 0, "empty board");
+        assert!(config.connect > 0, "need at least one in a row to win");
+        Ok(Self { config, x: 0, o: 0, to_play: Player::X, winner: None })
+    }
+
+    pub fn config(&self) -> GameConfig {
+        self.config
+    }
+    pub fn width(&self) -> usize {
+        self.config.width
+    }
+    pub fn height(&self) -> usize {
+        self.config.height
+    }
+    pub fn to_play(&self) -> Player {
+        self.to_play
+    }
+
+    fn stride(&self) -> usize {
+        self.height() + 1
+    }
+    fn bit(&self, col: usize, row: usize) -> u128 {
+        1 << (col * self.stride() + row)
+    }
+    fn column_mask(&self, col: usize) -> u128 {
+        ((1 << self.height()) - 1) << (col * self.stride())
+    }
+    fn pieces(&self, player: Player) -> u128 {
+        match player {
+            Player::X => self.x,
+            Player::O => self.o,
+        }
+    }
+
+    // The column heights aren't stored: they're just how many bits are set
+    // in each column.
+    pub fn column_height(&self, col: usize) -> usize {
+        ((self.x | self.o) & self.column_mask(col)).count_ones() as usize
+    }
+
+    // A number that tells positions apart: the pieces of the player to
+    // move, plus every piece, plus one bit at the bottom of each column.
+    // In each column the carries leave the mover's pieces with a single 1
+    // just above the top piece, which marks how full the column is.
+    pub fn key(&self) -> u128 {
+        let bottom: u128 = (0..self.width()).map(|col| self.bit(col, 0)).sum();
+        self.pieces(self.to_play) + (self.x | self.o) + bottom
+    }
+
+    // The position reflected left to right.
+    pub fn mirrored(&self) -> Self {
+        let (stride, last) = (self.stride(), self.width() - 1);
+        let flip = |bits: u128| {
+            (0..self.width()).fold(0, |flipped, col| {
+                let column = (bits >> (col * stride)) & self.column_mask(0);
+                flipped | column << ((last - col) * stride)
+            })
+        };
+        Self { x: flip(self.x), o: flip(self.o), ..*self }
+    }
+
+    pub fn try_get(
+        &self,
+        col: usize,
+        row: usize,
+    ) -> Result<Option<Player>, BoardError> {
+        if col >= self.width() {
+            return Err(BoardError::ColumnOutOfRange(col));
+        }
+        if row >= self.height() {
+            return Err(BoardError::RowOutOfRange(row));
+        }
+        let bit = self.bit(col, row);
+        Ok(if self.x & bit != 0 {
+            Some(Player::X)
+        } else if self.o & bit != 0 {
+            Some(Player::O)
+        } else {
+            None
+        })
+    }
+    pub fn try_playable(&self, col: usize) -> Result<bool, BoardError> {
+        if col >= self.width() {
+            return Err(BoardError::ColumnOutOfRange(col));
+        }
+        Ok(self.column_height(col) < self.height())
+    }
+    pub fn try_play(
+        &mut self,
+        col: usize,
+        player: Player,
+    ) -> Result<(), BoardError> {
+        let playable = self.try_playable(col)?;
+        if self.is_over() {
+            return Err(BoardError::GameOver);
+        }
+        if !playable {
+            return Err(BoardError::ColumnFull(col));
+        }
+        let bit = self.bit(col, self.column_height(col));
+        match player {
+            Player::X => self.x |= bit,
+            Player::O => self.o |= bit,
+        }
+        self.to_play = player.other();
+        if self.has_run(self.pieces(player)) {
+            self.winner = Some(player);
+        }
+        Ok(())
+    }
+
+    pub fn get(&self, col: usize, row: usize) -> Option<Player> {
+        self.try_get(col, row).unwrap_or(None)
+    }
+    pub fn playable(&self, col: usize) -> bool {
+        self.try_playable(col) == Ok(true)
+    }
+    pub fn play(&mut self, col: usize, player: Player) {
+        if let Err(err) = self.try_play(col, player) {
+            panic!("illegal play in column {}: {}", col, err);
+        }
+    }
+    pub fn make_move(&mut self, col: usize) -> Result<(), BoardError> {
+        let player = self.to_play;
+        self.try_play(col, player)
+    }
+
+    pub fn valid_plays(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.width()).filter(move |&i| self.playable(i))
+    }
+
+    pub fn winner(&self) -> Option<Player> {
+        self.winner
+    }
+
+    pub fn is_over(&self) -> bool {
+        let filled = (self.x | self.o).count_ones() as usize;
+        self.winner.is_some() || filled == self.width() * self.height()
+    }
+
+    // Does mask contain config.connect set bits in a row in any direction?
+    // Shifting by 1 moves up a column, by stride moves across a row, and by
+    // stride - 1 or stride + 1 moves along the diagonals. ANDing the shifted
+    // copies leaves a 1 only where a whole run starts.
+    fn has_run(&self, mask: u128) -> bool {
+        let stride = self.stride() as u32;
+        [1, stride, stride - 1, stride + 1].iter().any(|&shift| {
+            let mut run = mask;
+            for k in 1..self.config.connect as u32 {
+                run &= mask.checked_shr(shift * k).unwrap_or(0);
+            }
+            run != 0
+        })
+    }
+}
+
+impl TryFrom<&Game> for BitBoard {
+    type Error = ConvertError;
+
+    fn try_from(game: &Game) -> Result<Self, ConvertError> {
+        game.require_classic()?;
+        let mut bits = Self::new(game.config)?;
+        for (col, column) in game.board.iter().enumerate() {
+            for (row, &player) in column.iter().enumerate() {
+                match player {
+                    Player::X => bits.x |= bits.bit(col, row),
+                    Player::O => bits.o |= bits.bit(col, row),
+                }
+            }
+        }
+        bits.to_play = game.to_play;
+        bits.winner = game.winner();
+        Ok(bits)
+    }
+}
+
+impl From<&BitBoard> for Game {
+    fn from(bits: &BitBoard) -> Self {
+        let board = (0..bits.width())
+            .map(|col| {
+                (0..bits.column_height(col))
+                    .filter_map(|row| bits.get(col, row))
+                    .collect()
+            })
+            .collect();
+        Game::from_columns(bits.config, board, bits.to_play)
+    }
+}
+
+#[test]
+fn test_bitboard_matches_game() {
+    super::test_random_games(0xb17, |game| {
+        let bits = BitBoard::try_from(game).unwrap();
+        assert_eq!(bits.winner(), game.winner());
+        assert_eq!(bits.is_over(), game.is_over());
+        assert_eq!(bits.to_play(), game.to_play());
+        assert!(bits.valid_plays().eq(game.valid_plays()));
+        for col in 0..game.width() {
+            assert_eq!(bits.playable(col), game.playable(col));
+            for row in 0..game.height() {
+                assert_eq!(bits.get(col, row), game.get(col, row));
+            }
+        }
+        assert_eq!(&Game::from(&bits), game);
+    });
+}
+
+#[test]
+fn test_bitboard_plays_like_game() {
+    // Play the same moves on both and compare the results of each play.
+    let config = GameConfig::standard();
+    let mut game = Game::with_config(config);
+    let mut bits = BitBoard::new(config).unwrap();
+    for &col in &[3, 3, 3, 3, 3, 3, 3, 4, 2, 4, 5, 4, 6, 8, 1, 0, 4] {
+        assert_eq!(bits.make_move(col), game.make_move(col));
+        assert_eq!(bits.winner(), game.winner());
+    }
+    assert_eq!(bits.winner(), Some(Player::X));
+}
+
+#[test]
+fn test_bitboard_key_and_mirror() {
+    let mut keys = std::collections::HashMap::new();
+    super::test_random_games(0x4e7, |game| {
+        let bits = BitBoard::try_from(game).unwrap();
+        let mirrored = BitBoard::try_from(&game.mirrored()).unwrap();
+        assert_eq!(bits.mirrored(), mirrored);
+        assert_eq!(bits.mirrored().mirrored(), bits);
+        // Different positions, different keys.
+        let previous = keys.insert((game.config(), bits.key()), bits);
+        assert!(previous.is_none_or(|previous| previous == bits));
+    });
+}
+
+#[test]
+fn test_bitboard_too_large() {
+    let config = GameConfig::new(16, 8);
+    assert_eq!(BitBoard::new(config), Err(TooLarge(config)));
+    let game = Game::with_config(config);
+    let error = ConvertError::TooLarge(TooLarge(config));
+    assert_eq!(BitBoard::try_from(&game), Err(error));
+    assert!(BitBoard::new(GameConfig::new(16, 7)).is_ok());
+}
+
+#[test]
+fn test_bitboard_classic_only() {
+    use super::rules::Rules;
+    for &rules in &[Rules::PopOut, Rules::Pop10] {
+        let game = Game::with_rules(GameConfig::standard(), rules);
+        let error = ConvertError::ClassicOnly(ClassicOnly(rules));
+        assert_eq!(BitBoard::try_from(&game), Err(error));
+    }
+}
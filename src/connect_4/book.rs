@@ -0,0 +1,316 @@
+/*
+    An opening book: search results for the first few moves, worked out
+    ahead of time.
+
+    The early game is where the engine has the most to search and the
+    least to go on, and every game passes through it. So we search every
+    position up to a few plies deep once, save the results to a file, and
+    look them up instead of searching during play.
+
+    Positions are keyed by their canonical hash, so a position and its
+    mirror image share one entry. The move stored is the one for the
+    canonical orientation, and is flipped back on the way out. Like the
+    engine, the book only knows the classic rules.
+
+    The file is binary and small: a header, then one 15-byte record per
+    position, with every number little-endian.
+        "C4BK" version width height connect depth count(u32)
+        key(u64) best(u8) score(i32) outcome(u8) plies(u8)
+*/
+
+use std::collections::{HashMap, HashSet};
+use std::convert::TryInto;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::engine::{Engine, Outcome, SearchResult};
+use super::evaluation::Evaluate;
+use super::rules::ClassicOnly;
+use super::{Game, GameConfig};
+
+const MAGIC: &[u8; 4] = b"C4BK";
+const VERSION: u8 = 1;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BookEntry {
+    // The best move, in the canonical orientation.
+    pub best: usize,
+    pub score: i32,
+    pub outcome: Outcome,
+}
+
+// Returned by Book::generate for a depth of 0: the book stores moves, and
+// a search that looks no moves ahead doesn't pick one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ZeroDepth;
+
+impl fmt::Display for ZeroDepth {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an opening book needs a search depth of at least 1")
+    }
+}
+
+impl error::Error for ZeroDepth {}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Book {
+    pub config: GameConfig,
+    // How deep each position was searched.
+    pub depth: usize,
+    entries: HashMap<u64, BookEntry>,
+}
+
+impl Book {
+    // Searches every position reachable in at most `plies` moves to the
+    // given depth. Mirror images are only searched once.
+    pub fn generate(
+        config: GameConfig,
+        plies: usize,
+        depth: usize,
+    ) -> Result<Self, ZeroDepth> {
+        if depth == 0 {
+            return Err(ZeroDepth);
+        }
+        let mut engine = Engine::new(depth);
+        let mut book = Book { config, depth, entries: HashMap::new() };
+        let mut seen = HashSet::new();
+        let mut frontier = vec![Game::with_config(config)];
+        for ply in 0..=plies {
+            let mut next = Vec::new();
+            for game in frontier {
+                if game.is_over() || !seen.insert(game.canonical_zobrist()) {
+                    continue;
+                }
+                // Always Ok: the game is classic.
+                if let Ok(result) = engine.search(&game) {
+                    book.insert(&game, result);
+                }
+                if ply < plies {
+                    for col in game.valid_plays() {
+                        let mut child = game.clone();
+                        child.make_move(col).unwrap();
+                        next.push(child);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        Ok(book)
+    }
+
+    // A result with no move (there are none for a game in progress)
+    // isn't stored.
+    fn insert(&mut self, game: &Game, result: SearchResult) {
+        if let Some(best) = result.best {
+            let best = flip(game, best);
+            let (score, outcome) = (result.score, result.outcome);
+            let entry = BookEntry { best, score, outcome };
+            self.entries.insert(game.canonical_zobrist(), entry);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // The stored result for this position, with the move in the game's
+    // own orientation. None if the position isn't in the book.
+    pub fn lookup(
+        &self,
+        game: &Game,
+    ) -> Result<Option<SearchResult>, ClassicOnly> {
+        game.require_classic()?;
+        if game.config() != self.config {
+            return Ok(None);
+        }
+        let entry = match self.entries.get(&game.canonical_zobrist()) {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        Ok(Some(SearchResult {
+            best: Some(flip(game, entry.best)),
+            score: entry.score,
+            outcome: entry.outcome,
+            nodes: 0,
+            depth: self.depth,
+        }))
+    }
+
+    // The book's answer if it has one, otherwise the engine's.
+    pub fn search<E: Evaluate>(
+        &self,
+        game: &Game,
+        engine: &mut Engine<E>,
+    ) -> Result<SearchResult, ClassicOnly> {
+        match self.lookup(game)? {
+            Some(result) => Ok(result),
+            None => engine.search(game),
+        }
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let config = self.config;
+        out.write_all(MAGIC)?;
+        out.write_all(&[
+            VERSION,
+            small(config.width)?,
+            small(config.height)?,
+            small(config.connect)?,
+            small(self.depth)?,
+        ])?;
+        out.write_all(&(self.entries.len() as u32).to_le_bytes())?;
+        // Sorted, so the same book always makes the same file.
+        let mut keys: Vec<&u64> = self.entries.keys().collect();
+        keys.sort_unstable();
+        for key in keys {
+            let entry = self.entries[key];
+            let (kind, plies) = match entry.outcome {
+                Outcome::Unknown => (0, 0),
+                Outcome::Draw => (1, 0),
+                Outcome::Win(plies) => (2, plies),
+                Outcome::Loss(plies) => (3, plies),
+            };
+            out.write_all(&key.to_le_bytes())?;
+            out.write_all(&[small(entry.best)?])?;
+            out.write_all(&entry.score.to_le_bytes())?;
+            out.write_all(&[kind, small(plies)?])?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(input: &mut R) -> io::Result<Self> {
+        let mut header = [0; 13];
+        input.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid("not an opening book"));
+        }
+        let [width, height, connect, depth] =
+            [header[5], header[6], header[7], header[8]].map(usize::from);
+        if width == 0 || height == 0 || connect == 0 {
+            return Err(invalid("empty board"));
+        }
+        let config = GameConfig { width, height, connect };
+        let count = u32::from_le_bytes(header[9..13].try_into().unwrap());
+
+        let mut entries = HashMap::new();
+        for _ in 0..count {
+            let mut record = [0; 15];
+            input.read_exact(&mut record)?;
+            let key = u64::from_le_bytes(record[..8].try_into().unwrap());
+            let best = usize::from(record[8]);
+            let score = i32::from_le_bytes(record[9..13].try_into().unwrap());
+            let plies = usize::from(record[14]);
+            let outcome = match record[13] {
+                0 => Outcome::Unknown,
+                1 => Outcome::Draw,
+                2 => Outcome::Win(plies),
+                3 => Outcome::Loss(plies),
+                _ => return Err(invalid("bad outcome")),
+            };
+            if best >= width {
+                return Err(invalid("move off the board"));
+            }
+            entries.insert(key, BookEntry { best, score, outcome });
+        }
+        Ok(Book { config, depth, entries })
+    }
+}
+
+// Moves are stored for the canonical orientation; this converts either
+// way, since flipping twice gets back where we started.
+fn flip(game: &Game, col: usize) -> usize {
+    if game.is_canonical() {
+        col
+    } else {
+        game.width() - 1 - col
+    }
+}
+
+// Everything in the file that isn't a key, score or count is one byte.
+fn small(n: usize) -> io::Result<u8> {
+    if n > usize::from(u8::MAX) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "too big for an opening book",
+        ));
+    }
+    Ok(n as u8)
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[test]
+fn test_book_generate_and_lookup() {
+    let config = GameConfig::new(4, 4);
+    assert_eq!(Book::generate(config, 2, 0), Err(ZeroDepth));
+    let book = Book::generate(config, 2, 16).unwrap();
+    // 1 empty board, 2 first moves up to mirroring, 8 second moves.
+    assert_eq!(book.len(), 11);
+    let mut engine = Engine::new(16);
+    let mut game = Game::with_config(config);
+    for &col in &[0, 3] {
+        game.make_move(col).unwrap();
+        let looked_up = book.lookup(&game).unwrap().unwrap();
+        let searched = engine.search(&game).unwrap();
+        assert_eq!(looked_up.score, searched.score);
+        assert_eq!(looked_up.outcome, searched.outcome);
+        // The move works in this orientation, not just the stored one.
+        let mut after = game.clone();
+        after.make_move(looked_up.best.unwrap()).unwrap();
+        let expected = match searched.outcome {
+            Outcome::Win(plies) => Outcome::Loss(plies - 1),
+            Outcome::Loss(plies) => Outcome::Win(plies - 1),
+            outcome => outcome,
+        };
+        assert_eq!(engine.search(&after).unwrap().outcome, expected);
+    }
+    // Too deep for the book: searched instead.
+    game.make_move(1).unwrap();
+    assert_eq!(book.lookup(&game).unwrap(), None);
+    assert!(book.search(&game, &mut engine).unwrap().nodes > 0);
+    // A different board is never in the book.
+    assert_eq!(book.lookup(&Game::new()).unwrap(), None);
+}
+
+#[test]
+fn test_book_file() {
+    let book = Book::generate(GameConfig::standard(), 2, 4).unwrap();
+    let mut bytes = Vec::new();
+    book.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 13 + 15 * book.len());
+    assert_eq!(Book::read_from(&mut &bytes[..]).unwrap(), book);
+
+    let path = std::env::temp_dir().join("connect_4_book_test.bin");
+    book.save(&path).unwrap();
+    assert_eq!(Book::load(&path).unwrap(), book);
+    std::fs::remove_file(&path).unwrap();
+
+    let error = Book::read_from(&mut &b"PGN!"[..]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    let mut wrong = bytes.clone();
+    wrong[0] = b'X';
+    let error = Book::read_from(&mut &wrong[..]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    // Cut off partway through the entries.
+    let error = Book::read_from(&mut &bytes[..bytes.len() - 1]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
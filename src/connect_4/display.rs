@@ -0,0 +1,204 @@
+/*
+    Drawing a Game as text.
+
+    Display is the trait behind "{}" in format strings, the way Debug is
+    behind "{:?}". Unlike Debug it can't be derived: we decide what the
+    user sees. Here that's the board drawn bottom-up, column numbers
+    underneath, and whose turn it is.
+*/
+
+use std::fmt;
+
+use super::{Game, GameStatus, Player};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Style {
+    // Plain characters that work everywhere.
+    Ascii,
+    // Box-drawing characters and round pieces.
+    Unicode,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RenderOptions {
+    pub style: Style,
+    // Draw the pieces of the winning line differently.
+    pub highlight_win: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self { style: Style::Ascii, highlight_win: true }
+    }
+}
+
+// The characters each style is drawn with.
+struct Glyphs {
+    x: char,
+    o: char,
+    x_win: char,
+    o_win: char,
+    empty: char,
+    wall: char,
+    floor: char,
+    left_corner: char,
+    right_corner: char,
+}
+
+impl Style {
+    fn glyphs(self) -> Glyphs {
+        match self {
+            // There's no bold in plain text, so the winning line is drawn
+            // with stars; the status line below says whose they are.
+            Style::Ascii => Glyphs {
+                x: 'X',
+                o: 'O',
+                x_win: '*',
+                o_win: '*',
+                empty: '.',
+                wall: '|',
+                floor: '-',
+                left_corner: '+',
+                right_corner: '+',
+            },
+            Style::Unicode => Glyphs {
+                x: '●',
+                o: '○',
+                x_win: '◉',
+                o_win: '◎',
+                empty: '·',
+                wall: '│',
+                floor: '─',
+                left_corner: '└',
+                right_corner: '┘',
+            },
+        }
+    }
+}
+
+// A game together with how to draw it. Returned by Game::render so that
+// callers can write println!("{}", game.render(options)).
+pub struct Render<'a> {
+    game: &'a Game,
+    options: RenderOptions,
+}
+
+impl Game {
+    pub fn render(&self, options: RenderOptions) -> Render<'_> {
+        Render { game: self, options }
+    }
+}
+
+impl<'a> fmt::Display for Render<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let game = self.game;
+        let glyphs = self.options.style.glyphs();
+        let status = game.status();
+        let winning_cells = match status {
+            GameStatus::Won { ref cells, .. } if self.options.highlight_win => {
+                cells.clone()
+            }
+            _ => Vec::new(),
+        };
+        // Wide enough for the biggest column number plus a space.
+        let cell_width = game.width().to_string().len() + 1;
+
+        for row in (0..game.height()).rev() {
+            write!(f, "{}", glyphs.wall)?;
+            for col in 0..game.width() {
+                let winning = winning_cells.contains(&(col, row));
+                let glyph = match (game.get(col, row), winning) {
+                    (Some(Player::X), false) => glyphs.x,
+                    (Some(Player::O), false) => glyphs.o,
+                    (Some(Player::X), true) => glyphs.x_win,
+                    (Some(Player::O), true) => glyphs.o_win,
+                    (None, _) => glyphs.empty,
+                };
+                write!(f, "{:>width$}", glyph, width = cell_width)?;
+            }
+            writeln!(f, " {}", glyphs.wall)?;
+        }
+
+        let floor: String =
+            (0..game.width() * cell_width + 1).map(|_| glyphs.floor).collect();
+        writeln!(f, "{}{}{}", glyphs.left_corner, floor, glyphs.right_corner)?;
+        write!(f, " ")?;
+        for col in 0..game.width() {
+            // Columns are numbered from 1 for people.
+            write!(f, "{:>width$}", col + 1, width = cell_width)?;
+        }
+        writeln!(f)?;
+
+        let name = |player| match player {
+            Player::X => glyphs.x,
+            Player::O => glyphs.o,
+        };
+        match status {
+            GameStatus::InProgress { to_play } => {
+                write!(f, "{} to play", name(to_play))
+            }
+            GameStatus::Won { player, .. } => {
+                write!(f, "{} wins", name(player))
+            }
+            GameStatus::Draw => write!(f, "Draw"),
+        }
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.render(RenderOptions::default()))
+    }
+}
+
+#[cfg(test)]
+fn test_game() -> Game {
+    let mut game = Game::with_config(super::GameConfig::new(4, 4));
+    for &col in &[0, 1, 1, 2, 2, 3, 2] {
+        game.make_move(col).unwrap();
+    }
+    game
+}
+
+#[test]
+fn test_display_ascii() {
+    let expected = "\
+| . . . . |
+| . . X . |
+| . X X . |
+| X O O O |
++---------+
+  1 2 3 4
+O to play";
+    assert_eq!(format!("{}", test_game()), expected);
+}
+
+#[test]
+fn test_display_unicode_win() {
+    let mut game = test_game();
+    for &col in &[3, 0, 3, 3] {
+        game.make_move(col).unwrap();
+    }
+    let options = RenderOptions { style: Style::Unicode, highlight_win: true };
+    let expected = "\
+│ · · · ◉ │
+│ · · ◉ ○ │
+│ ● ◉ ● ○ │
+│ ◉ ○ ○ ○ │
+└─────────┘
+  1 2 3 4
+● wins";
+    assert_eq!(format!("{}", game.render(options)), expected);
+    let options = RenderOptions { style: Style::Ascii, highlight_win: false };
+    assert!(format!("{}", game.render(options)).starts_with("| . . . X |"));
+}
+
+#[test]
+fn test_display_wide() {
+    let game = Game::new();
+    let text = format!("{}", game);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines[0], "|  .  .  .  .  .  .  .  .  .  . |");
+    assert_eq!(lines[6], "   1  2  3  4  5  6  7  8  9 10");
+    assert_eq!(lines[7], "X to play");
+}
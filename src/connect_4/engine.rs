@@ -0,0 +1,786 @@
+/*
+    A Connect 4 engine: negamax search with alpha-beta pruning.
+
+    Negamax is minimax written from the point of view of whoever is to
+    move: my score for a position is minus my opponent's score for it, so
+    one function handles both players. Alpha-beta pruning skips moves that
+    can't change the result: once a reply refutes a move, the rest of the
+    replies don't matter.
+
+    With a time limit instead of a depth, the engine searches to depth 1,
+    then 2, then 3... until time runs out (iterative deepening). The
+    shallow searches are cheap next to the deep ones, and they fill the
+    transposition table with good moves to try first at the next depth.
+
+    search_parallel splits the work at the root: after searching the
+    first move on its own, each thread takes the next untried move and
+    searches the position after it with an engine of its own, and the
+    best of the results wins. The threads share the best score so far, so
+    a move that can't match it is ruled out with a cheap null-window
+    search (alpha and beta one apart) instead of being searched in full.
+    With more threads than moves, the extra threads have nothing to do.
+
+    The engine only plays the classic rules (see rules.rs), so searching a
+    game played by any others is an error.
+*/
+
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::evaluation::{Evaluate, Weights};
+use super::rules::ClassicOnly;
+use super::transposition::{Bound, Entry, TranspositionTable};
+use super::{Game, GameConfig};
+
+// Scores are from the point of view of the player to move. A win k plies
+// from the start of the search scores WIN - k, so that quicker wins (and
+// slower losses) are preferred; a draw scores 0.
+pub const WIN: i32 = 1_000_000;
+// Anything beyond this is a forced win or loss.
+const WIN_BOUND: i32 = WIN - 10_000;
+const INFINITY: i32 = WIN + 1;
+// Evaluations are clamped to this, so they can't be mistaken for a win.
+const MAX_EVAL: i32 = WIN_BOUND - 1;
+
+const TABLE_SIZE: usize = 1 << 16;
+
+// How many nodes to search between looks at the clock.
+const CLOCK_INTERVAL: u64 = 1024;
+
+// What a score means, in plies from the searched position.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    Win(usize),
+    Loss(usize),
+    Draw,
+    // The search ran out of depth before finding a result.
+    Unknown,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SearchResult {
+    // None if the game is already over.
+    pub best: Option<usize>,
+    pub score: i32,
+    pub outcome: Outcome,
+    pub nodes: u64,
+    // How deep the search went. With a time limit, the deepest search that
+    // finished; nodes counts every search, including one cut short.
+    pub depth: usize,
+}
+
+// E scores the positions where the search stops early; see evaluation.rs.
+// Writing E = Weights makes it optional: a plain Engine uses the default.
+#[derive(Clone, Debug)]
+pub struct Engine<E = Weights> {
+    // How many plies ahead to look. A depth of at least the number of
+    // empty cells searches to the end of the game, so the answer is exact.
+    // Depth 0 searches 1 ply anyway: a search always has a move to give.
+    pub depth: usize,
+    pub evaluator: E,
+    nodes: u64,
+    // Did any line of play stop early because of the depth limit?
+    horizon: bool,
+    root_best: Option<usize>,
+    // The best move from the last depth of iterative deepening, which
+    // goes first at the next depth.
+    root_first: Option<usize>,
+    // Set when searching against the clock, and stopped once it's passed.
+    deadline: Option<Instant>,
+    stopped: bool,
+    // Kept between searches: positions from the last move are often
+    // reached again by the next one. Cleared if the board changes shape.
+    table: TranspositionTable,
+    table_config: Option<GameConfig>,
+}
+
+impl Engine {
+    pub fn new(depth: usize) -> Self {
+        Self::with_evaluator(depth, Weights::default())
+    }
+
+    pub fn with_table(depth: usize, table: TranspositionTable) -> Self {
+        Self::from_parts(depth, Weights::default(), table)
+    }
+}
+
+impl<E: Evaluate> Engine<E> {
+    pub fn with_evaluator(depth: usize, evaluator: E) -> Self {
+        Self::from_parts(depth, evaluator, TranspositionTable::new(TABLE_SIZE))
+    }
+
+    fn from_parts(
+        depth: usize,
+        evaluator: E,
+        table: TranspositionTable,
+    ) -> Self {
+        Self {
+            depth,
+            evaluator,
+            nodes: 0,
+            horizon: false,
+            root_best: None,
+            root_first: None,
+            deadline: None,
+            stopped: false,
+            table,
+            table_config: None,
+        }
+    }
+
+    pub fn search(&mut self, game: &Game) -> Result<SearchResult, ClassicOnly> {
+        game.require_classic()?;
+        Ok(self.search_classic(game))
+    }
+
+    // search, for a game already known to be classic.
+    fn search_classic(&mut self, game: &Game) -> SearchResult {
+        self.search_window(game, -INFINITY, INFINITY)
+    }
+
+    // A search that only needs to know the score if it's strictly between
+    // alpha and beta: outside the window, the score it returns is a bound
+    // (at most alpha, or at least beta) rather than the exact value.
+    fn search_window(
+        &mut self,
+        game: &Game,
+        alpha: i32,
+        beta: i32,
+    ) -> SearchResult {
+        self.start(game);
+        let depth = self.depth.max(1);
+        self.search_window_depth(&mut game.clone(), depth, alpha, beta)
+    }
+
+    // Searches deeper and deeper until the time is up, then returns the
+    // result of the deepest search that finished. The search to depth 1
+    // always finishes, however short the limit, so there's always a move.
+    // Stops early once the result is certain.
+    pub fn search_for(
+        &mut self,
+        game: &Game,
+        limit: Duration,
+    ) -> Result<SearchResult, ClassicOnly> {
+        game.require_classic()?;
+        let deadline = Instant::now() + limit;
+        self.start(game);
+        let mut game = game.clone();
+        let mut result = self.search_depth(&mut game, 1);
+        let mut nodes = result.nodes;
+        self.deadline = Some(deadline);
+        while result.outcome == Outcome::Unknown && result.depth < empty(&game)
+        {
+            if Instant::now() >= deadline {
+                break;
+            }
+            self.root_first = result.best;
+            let next = self.search_depth(&mut game, result.depth + 1);
+            nodes += next.nodes;
+            if self.stopped {
+                break;
+            }
+            result = next;
+        }
+        self.deadline = None;
+        self.stopped = false;
+        self.root_first = None;
+        Ok(SearchResult { nodes, ..result })
+    }
+
+    fn start(&mut self, game: &Game) {
+        if self.table_config != Some(game.config()) {
+            self.table.clear();
+            self.table_config = Some(game.config());
+        }
+        self.table.new_search();
+    }
+
+    // Search on a copy, playing and undoing moves as we go rather than
+    // cloning the game at every node.
+    fn search_depth(&mut self, game: &mut Game, depth: usize) -> SearchResult {
+        self.search_window_depth(game, depth, -INFINITY, INFINITY)
+    }
+
+    fn search_window_depth(
+        &mut self,
+        game: &mut Game,
+        depth: usize,
+        alpha: i32,
+        beta: i32,
+    ) -> SearchResult {
+        self.nodes = 0;
+        self.horizon = false;
+        self.root_best = None;
+        let score = self.negamax(game, depth, 0, alpha, beta);
+        SearchResult {
+            best: self.root_best,
+            score,
+            outcome: self.outcome(score),
+            nodes: self.nodes,
+            depth,
+        }
+    }
+
+    fn out_of_time(&mut self) -> bool {
+        if !self.stopped && self.nodes.is_multiple_of(CLOCK_INTERVAL) {
+            if let Some(deadline) = self.deadline {
+                self.stopped = Instant::now() >= deadline;
+            }
+        }
+        self.stopped
+    }
+
+    fn negamax(
+        &mut self,
+        game: &mut Game,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+    ) -> i32 {
+        self.nodes += 1;
+        // Once time is up the result doesn't matter: it gets thrown away.
+        if self.out_of_time() {
+            return 0;
+        }
+        // Only the player who just moved can have won.
+        if game.winner().is_some() {
+            return -(WIN - ply as i32);
+        }
+        if game.is_over() {
+            return 0;
+        }
+        if depth == 0 {
+            self.horizon = true;
+            return self.evaluator.evaluate(game).clamp(-MAX_EVAL, MAX_EVAL);
+        }
+
+        // Mirror images share an entry, so moves are stored as they'd be
+        // in the canonical orientation and flipped back here.
+        let canonical = game.is_canonical();
+        let last = game.width() - 1;
+        let flip = move |col: usize| if canonical { col } else { last - col };
+        let key = game.canonical_zobrist();
+        let mut table_move = if ply == 0 { self.root_first } else { None };
+        if let Some(entry) = self.table.get(key) {
+            table_move = table_move.or(entry.best.map(flip));
+            // At the root we need a move, not just a score, so keep going.
+            if entry.depth >= depth && ply > 0 {
+                let score = from_table(entry.score, ply);
+                let enough = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => score >= beta,
+                    Bound::Upper => score <= alpha,
+                };
+                if enough {
+                    if !is_win_or_loss(score) && entry.depth < empty(game) {
+                        // This result might have come from the horizon.
+                        self.horizon = true;
+                    }
+                    return score;
+                }
+            }
+        }
+
+        let original_alpha = alpha;
+        let mut best = -INFINITY;
+        let mut best_move = None;
+        for col in ordered_plays(game, table_move) {
+            game.make_move(col).unwrap();
+            let score = -self.negamax(game, depth - 1, ply + 1, -beta, -alpha);
+            game.undo();
+            if self.stopped {
+                return 0;
+            }
+            if score > best {
+                best = score;
+                best_move = Some(col);
+            }
+            if best > alpha {
+                alpha = best;
+            }
+            if alpha >= beta {
+                // The opponent already has a better option elsewhere, so
+                // they'll never let us reach this position.
+                break;
+            }
+        }
+
+        if ply == 0 {
+            self.root_best = best_move;
+        }
+        let bound = if best <= original_alpha {
+            Bound::Upper
+        } else if best >= beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+        self.table.store(Entry {
+            key,
+            depth,
+            score: to_table(best, ply),
+            bound,
+            best: best_move.map(flip),
+        });
+        best
+    }
+
+    fn outcome(&self, score: i32) -> Outcome {
+        outcome(score, self.horizon)
+    }
+}
+
+// Send: the evaluator is moved into other threads. Clone: each thread
+// gets its own copy.
+impl<E: Evaluate + Clone + Send> Engine<E> {
+    // The same value as search, spread over up to `threads` threads.
+    pub fn search_parallel(
+        &mut self,
+        game: &Game,
+        threads: usize,
+    ) -> Result<SearchResult, ClassicOnly> {
+        game.require_classic()?;
+        // Each thread searches one ply less, so depth 1 has nothing to
+        // split.
+        if self.depth <= 1 || game.is_over() || threads <= 1 {
+            return Ok(self.search_classic(game));
+        }
+        let plays = ordered_plays(game, None);
+        let depth = self.depth - 1;
+        // The first move in order is often the best, so it's searched on
+        // its own before the rest are split up: that way every thread has a
+        // score to compare against from the start.
+        let mut engine = Engine::with_evaluator(depth, self.evaluator.clone());
+        let mut after = game.clone();
+        after.make_move(plays[0]).unwrap();
+        let first = engine.search_classic(&after);
+        // The best score found so far, from here: a move that can't match
+        // it only needs a quick search that proves as much.
+        let best_so_far = AtomicI32::new(from_child(first.score));
+        let next = AtomicUsize::new(1);
+        // Each thread returns (index into plays, result after that play,
+        // whether the result's score is exact).
+        let mut results: Vec<(usize, SearchResult, bool)> =
+            thread::scope(|scope| {
+                let workers: Vec<_> = (0..threads.min(plays.len() - 1))
+                    .map(|_| {
+                        let (plays, next) = (&plays, &next);
+                        let best_so_far = &best_so_far;
+                        let evaluator = self.evaluator.clone();
+                        scope.spawn(move || {
+                            let mut engine =
+                                Engine::with_evaluator(depth, evaluator);
+                            let mut results = Vec::new();
+                            // fetch_add hands out every index exactly once,
+                            // however the threads interleave.
+                            loop {
+                                let i = next.fetch_add(1, Ordering::Relaxed);
+                                if i >= plays.len() {
+                                    return results;
+                                }
+                                let mut after = game.clone();
+                                after.make_move(plays[i]).unwrap();
+                                let best = best_so_far.load(Ordering::Relaxed);
+                                let (result, exact) =
+                                    search_child(&mut engine, &after, best);
+                                if exact {
+                                    best_so_far.fetch_max(
+                                        from_child(result.score),
+                                        Ordering::Relaxed,
+                                    );
+                                }
+                                results.push((i, result, exact));
+                            }
+                        })
+                    })
+                    .collect();
+                workers
+                    .into_iter()
+                    .flat_map(|worker| worker.join().unwrap())
+                    .collect()
+            });
+        results.push((0, first, true));
+        // Back in move order, so ties go to the same move every time.
+        results.sort_by_key(|&(i, _, _)| i);
+
+        // A move whose score isn't exact is worse than the best one, which
+        // always has an exact score: nothing was better when it was
+        // searched.
+        let mut best = None;
+        let mut best_score = -INFINITY;
+        let mut horizon = false;
+        let mut nodes = 1;
+        for (i, result, exact) in results {
+            let score = from_child(result.score);
+            if exact && score > best_score {
+                best_score = score;
+                best = Some(plays[i]);
+            }
+            horizon |= result.outcome == Outcome::Unknown;
+            nodes += result.nodes;
+        }
+        Ok(SearchResult {
+            best,
+            score: best_score,
+            outcome: outcome(best_score, horizon),
+            nodes,
+            depth: self.depth,
+        })
+    }
+}
+
+// Searches the position after a root move, returning the result and
+// whether its score is exact. A null window first checks whether the move
+// can match best, the best score from the root so far: if it can't, the
+// bound is all we need, and it's much cheaper than the exact score.
+// Matching counts, not just beating, so that a move tied with the best
+// gets an exact score too and ties go to the first move however the
+// threads are scheduled.
+fn search_child<E: Evaluate>(
+    engine: &mut Engine<E>,
+    after: &Game,
+    best: i32,
+) -> (SearchResult, bool) {
+    // The move matches best exactly when the child's score is at most
+    // limit.
+    let limit = -to_child(best);
+    let result = engine.search_window(after, limit, limit + 1);
+    if result.score > limit {
+        return (result, false);
+    }
+    let mut exact = engine.search_classic(after);
+    exact.nodes += result.nodes;
+    (exact, true)
+}
+
+// The child's score is for the opponent, and a win or loss is one ply
+// further away from here.
+fn from_child(score: i32) -> i32 {
+    match -score {
+        score if score > WIN_BOUND => score - 1,
+        score if score < -WIN_BOUND => score + 1,
+        score => score,
+    }
+}
+
+// The inverse of from_child, up to sign: a score from here, one ply nearer
+// to any win or loss.
+fn to_child(score: i32) -> i32 {
+    match score {
+        score if score > WIN_BOUND => score + 1,
+        score if score < -WIN_BOUND => score - 1,
+        score => score,
+    }
+}
+
+// horizon: whether the search stopped anywhere because of its depth.
+fn outcome(score: i32, horizon: bool) -> Outcome {
+    if score > WIN_BOUND {
+        Outcome::Win((WIN - score) as usize)
+    } else if score < -WIN_BOUND {
+        Outcome::Loss((WIN + score) as usize)
+    } else if score == 0 && !horizon {
+        Outcome::Draw
+    } else {
+        Outcome::Unknown
+    }
+}
+
+fn is_win_or_loss(score: i32) -> bool {
+    score.abs() > WIN_BOUND
+}
+
+fn empty(game: &Game) -> usize {
+    game.width() * game.height() - game.filled
+}
+
+// Win and loss scores count plies from the root of the search, but a table
+// entry may be used by a later search from a different root. So the table
+// stores them counted from the entry's own position instead.
+fn to_table(score: i32, ply: usize) -> i32 {
+    if score > WIN_BOUND {
+        score + ply as i32
+    } else if score < -WIN_BOUND {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+fn from_table(score: i32, ply: usize) -> i32 {
+    if score > WIN_BOUND {
+        score - ply as i32
+    } else if score < -WIN_BOUND {
+        score + ply as i32
+    } else {
+        score
+    }
+}
+
+// Center columns first: they take part in the most lines, so they are
+// usually the best moves, and trying good moves first prunes more.
+// A move remembered from an earlier search goes before all of them.
+fn ordered_plays(game: &Game, first: Option<usize>) -> Vec<usize> {
+    let width = game.width();
+    let mut plays: Vec<usize> = game.valid_plays().collect();
+    // false sorts before true, then twice the distance from the center.
+    plays.sort_by_key(|&col| {
+        (Some(col) != first, (2 * col).abs_diff(width - 1))
+    });
+    plays
+}
+
+// Convenience wrapper for a one-off search.
+pub fn best_move(
+    game: &Game,
+    depth: usize,
+) -> Result<Option<usize>, ClassicOnly> {
+    Ok(Engine::new(depth).search(game)?.best)
+}
+
+// Plain minimax with no pruning, to check the engine against.
+#[cfg(test)]
+fn minimax(game: &mut Game, ply: i32) -> i32 {
+    if game.winner().is_some() {
+        return -(WIN - ply);
+    }
+    let plays: Vec<usize> = game.valid_plays().collect();
+    if plays.is_empty() {
+        return 0;
+    }
+    let mut best = -INFINITY;
+    for col in plays {
+        game.make_move(col).unwrap();
+        best = best.max(-minimax(game, ply + 1));
+        game.undo();
+    }
+    best
+}
+
+#[test]
+fn test_immediate_win() {
+    let game =
+        super::test_position(GameConfig::standard(), &[0, 0, 1, 1, 2, 2]);
+    let result = Engine::new(4).search(&game).unwrap();
+    assert_eq!(result.best, Some(3));
+    assert_eq!(result.outcome, Outcome::Win(1));
+}
+
+#[test]
+fn test_forced_block() {
+    let game = super::test_position(GameConfig::standard(), &[0, 0, 1, 1, 2]);
+    let result = Engine::new(4).search(&game).unwrap();
+    assert_eq!(result.best, Some(3));
+    assert_ne!(result.outcome, Outcome::Loss(2));
+}
+
+#[test]
+fn test_zugzwang() {
+    // Neither side can win immediately, but every move the player to move
+    // has gives the opponent a winning square.
+    for &(moves, plies) in &[
+        ("63034541155614305115620663653", 4),
+        ("10220064112644366503421140261", 10),
+    ] {
+        let moves: Vec<usize> =
+            moves.bytes().map(|b| (b - b'0') as usize).collect();
+        let game = super::test_position(GameConfig::standard(), &moves);
+        let result = Engine::new(42).search(&game).unwrap();
+        assert_eq!(result.outcome, Outcome::Loss(plies));
+    }
+}
+
+#[test]
+fn test_game_over() {
+    let game =
+        super::test_position(GameConfig::default(), &[0, 0, 1, 1, 2, 2, 3]);
+    let result = Engine::new(4).search(&game).unwrap();
+    assert_eq!(result.best, None);
+    assert_eq!(result.outcome, Outcome::Loss(0));
+}
+
+#[test]
+fn test_depth_limit_unknown() {
+    let result = Engine::new(2).search(&Game::new()).unwrap();
+    assert_eq!(result.outcome, Outcome::Unknown);
+    assert!(result.best.is_some());
+    // Depth 0 still finds a move.
+    let result = Engine::new(0).search(&Game::new()).unwrap();
+    assert!(result.best.is_some());
+    assert_eq!(result.depth, 1);
+}
+
+#[test]
+fn test_evaluator_used() {
+    // Rewards whoever has a piece in the bottom left corner.
+    #[derive(Clone, Debug)]
+    struct Corner;
+    impl Evaluate for Corner {
+        fn evaluate(&self, game: &Game) -> i32 {
+            match game.get(0, 0) {
+                Some(player) if player == game.to_play() => 10,
+                Some(_) => -10,
+                None => 0,
+            }
+        }
+    }
+    let game = Game::with_config(GameConfig::standard());
+    let result = Engine::with_evaluator(1, Corner).search(&game).unwrap();
+    assert_eq!((result.best, result.score), (Some(0), 10));
+    assert_eq!(result.outcome, Outcome::Unknown);
+    // The default weights like the center.
+    assert_eq!(Engine::new(1).search(&game).unwrap().best, Some(3));
+    // Huge evaluations are still not wins.
+    struct Huge;
+    impl Evaluate for Huge {
+        fn evaluate(&self, _game: &Game) -> i32 {
+            i32::MAX
+        }
+    }
+    let result = Engine::with_evaluator(2, Huge).search(&game).unwrap();
+    assert_eq!(result.outcome, Outcome::Unknown);
+}
+
+#[test]
+fn test_matches_minimax() {
+    let mut checked = 0;
+    super::test_random_games(0xab, |game| {
+        let empty = game.width() * game.height() - game.filled;
+        if game.is_over() || empty > 10 || checked >= 40 {
+            return;
+        }
+        checked += 1;
+        let result = Engine::new(empty).search(game).unwrap();
+        assert_eq!(result.score, minimax(&mut game.clone(), 0));
+        assert_ne!(result.outcome, Outcome::Unknown);
+        // The move it picks really does get that score.
+        let mut after = game.clone();
+        after.make_move(result.best.unwrap()).unwrap();
+        assert_eq!(-minimax(&mut after, 1), result.score);
+    });
+    assert!(checked > 0);
+}
+
+#[test]
+fn test_table_reused() {
+    // Searching the same position again is answered mostly from the table.
+    let game = super::test_position(GameConfig::new(4, 4), &[1, 1, 2]);
+    let mut engine = Engine::new(20);
+    let first = engine.search(&game).unwrap();
+    let second = engine.search(&game).unwrap();
+    assert_eq!(first.score, second.score);
+    assert_eq!(first.outcome, second.outcome);
+    assert!(second.nodes < first.nodes);
+    // So is its mirror image.
+    let mirrored = engine.search(&game.mirrored()).unwrap();
+    assert_eq!(mirrored.score, first.score);
+    assert!(mirrored.nodes < first.nodes);
+    let mut after = game.mirrored();
+    after.make_move(mirrored.best.unwrap()).unwrap();
+    assert_eq!(-minimax(&mut after, 1), first.score);
+}
+
+#[test]
+fn test_search_for_stops_when_certain() {
+    let game =
+        super::test_position(GameConfig::standard(), &[0, 0, 1, 1, 2, 2]);
+    let result =
+        Engine::new(0).search_for(&game, Duration::from_secs(60)).unwrap();
+    assert_eq!(result.best, Some(3));
+    assert_eq!(result.outcome, Outcome::Win(1));
+    assert_eq!(result.depth, 1);
+
+    // Small enough to solve completely, with the same answer as a search
+    // to the end.
+    let game = super::test_position(GameConfig::new(4, 4), &[1, 1, 2]);
+    let timed =
+        Engine::new(0).search_for(&game, Duration::from_secs(60)).unwrap();
+    let fixed = Engine::new(20).search(&game).unwrap();
+    assert_eq!((timed.score, timed.outcome), (fixed.score, fixed.outcome));
+    assert!(timed.depth <= 13);
+}
+
+#[test]
+fn test_search_for_deadline() {
+    let game = Game::with_config(GameConfig::standard());
+    // Even with no time at all, depth 1 is finished and gives a move.
+    let result =
+        Engine::new(0).search_for(&game, Duration::from_secs(0)).unwrap();
+    assert_eq!(result.depth, 1);
+    assert!(result.best.is_some());
+
+    let limit = Duration::from_millis(100);
+    let start = Instant::now();
+    let result = Engine::new(0).search_for(&game, limit).unwrap();
+    // Generous, since tests may share the machine.
+    assert!(start.elapsed() < limit * 10);
+    assert!(result.depth > 1);
+    assert!(result.best.is_some());
+    assert_eq!(result.outcome, Outcome::Unknown);
+    assert!(result.nodes > 0);
+}
+
+#[test]
+fn test_parallel_matches_serial() {
+    let mut checked = 0;
+    super::test_random_games(0x21, |game| {
+        let empty = empty(game);
+        if game.is_over() || empty > 12 || checked >= 30 {
+            return;
+        }
+        checked += 1;
+        let serial = Engine::new(empty).search(game).unwrap();
+        for &threads in &[2, 3, 8] {
+            let parallel =
+                Engine::new(empty).search_parallel(game, threads).unwrap();
+            assert_eq!(parallel.score, serial.score);
+            assert_eq!(parallel.outcome, serial.outcome);
+            // The move it picks really does get that score.
+            let mut after = game.clone();
+            after.make_move(parallel.best.unwrap()).unwrap();
+            assert_eq!(-minimax(&mut after, 1), serial.score);
+        }
+    });
+    assert!(checked > 0);
+}
+
+#[test]
+fn test_parallel_depth_limited() {
+    let game = super::test_position(GameConfig::standard(), &[3, 3, 2]);
+    let serial = Engine::new(5).search(&game).unwrap();
+    let parallel = Engine::new(5).search_parallel(&game, 4).unwrap();
+    assert_eq!(parallel.score, serial.score);
+    assert_eq!(parallel.outcome, Outcome::Unknown);
+    assert_eq!(parallel.depth, 5);
+    // However the threads are scheduled, the answer is the same (though
+    // the node count may not be).
+    for _ in 0..3 {
+        let again = Engine::new(5).search_parallel(&game, 4).unwrap();
+        assert_eq!((again.best, again.score), (parallel.best, parallel.score));
+    }
+    // Forced wins are counted from the root, as in the serial search.
+    let game =
+        super::test_position(GameConfig::standard(), &[0, 0, 1, 1, 2, 2]);
+    let result = Engine::new(4).search_parallel(&game, 4).unwrap();
+    assert_eq!(result.outcome, Outcome::Win(1));
+    assert_eq!(result.best, Some(3));
+}
+
+#[test]
+fn test_parallel_nodes() {
+    // The threads share the best score so far, so moves that can't beat
+    // it are ruled out cheaply: splitting the work shouldn't add much to
+    // it. (Searching every move with a full window took twice the nodes
+    // with two threads.)
+    let game = super::test_position(GameConfig::standard(), &[3, 3, 2]);
+    let serial = Engine::new(6).search(&game).unwrap();
+    for &(threads, limit) in [(2, 3), (8, 4)].iter() {
+        let parallel = Engine::new(6).search_parallel(&game, threads).unwrap();
+        assert_eq!(
+            (parallel.best, parallel.score),
+            (serial.best, serial.score)
+        );
+        assert!(parallel.nodes * 2 < serial.nodes * limit);
+    }
+}
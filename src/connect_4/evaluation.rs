@@ -0,0 +1,290 @@
+/*
+    Static evaluation: guessing who's ahead without searching further.
+
+    When the engine reaches its depth limit it needs a score for a game
+    that isn't over. Anything implementing Evaluate can provide one; the
+    engine is generic over it, so a different heuristic can be plugged in
+    without touching the search.
+
+    The default, Weights, looks at every block (the lines from
+    Game::blocks) and scores:
+    - lines only one player has pieces in, more for more pieces;
+    - pieces in the center column(s), which take part in the most lines;
+    - lines missing one piece whose empty cell is on the right row for
+      its owner. X, who moves first, tends to get to fill odd rows
+      (1, 3, 5, counting from 1 at the bottom) once the board fills up,
+      and O even rows; a threat on the other player's rows is worth less.
+
+    Weights can be written to and read from a small text file, one
+    "name = value" per line, so they can be tuned without recompiling.
+*/
+
+use std::error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::str::FromStr;
+
+#[cfg(test)]
+use super::{test_position, GameConfig};
+use super::{Game, Player};
+
+pub trait Evaluate {
+    // The score of a game that isn't over, for the player to move.
+    // Positive is good for them. The engine keeps scores well away from
+    // its win and loss scores, so any i32 is fine.
+    fn evaluate(&self, game: &Game) -> i32;
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Weights {
+    // line[k]: a block with k of a player's pieces and none of the
+    // other's. Missing entries count as 0.
+    pub line: Vec<i32>,
+    // Each piece in the center column (both of them on even widths).
+    pub center: i32,
+    // A block missing one piece, with the empty cell on its owner's rows.
+    pub parity: i32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self { line: vec![0, 1, 5, 20], center: 3, parity: 30 }
+    }
+}
+
+impl Weights {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, WeightsError> {
+        fs::read_to_string(path).map_err(WeightsError::Io)?.parse()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    // One player's share of the score. Added up as an i64, since weights
+    // read from a file can be as big as any i32 and there are many blocks.
+    fn score(&self, game: &Game, player: Player) -> i64 {
+        let connect = game.config().connect;
+        let mut score = 0;
+        for block in game.blocks() {
+            let mut mine = 0;
+            let mut empty = None;
+            let mut blocked = false;
+            for &(i, j) in &block {
+                match game.get(i, j) {
+                    Some(owner) if owner == player => mine += 1,
+                    Some(_) => blocked = true,
+                    None => empty = Some((i, j)),
+                }
+            }
+            if blocked {
+                continue;
+            }
+            score += i64::from(self.line.get(mine).cloned().unwrap_or(0));
+            if mine + 1 == connect {
+                // Rows count from 1 here: X's are the odd ones.
+                let row = empty.unwrap().1 + 1;
+                let odd = row % 2 == 1;
+                if odd == (player == Player::X) {
+                    score += i64::from(self.parity);
+                }
+            }
+        }
+        let width = game.width();
+        for col in (width - 1) / 2..=width / 2 {
+            for row in 0..game.height() {
+                if game.get(col, row) == Some(player) {
+                    score += i64::from(self.center);
+                }
+            }
+        }
+        score
+    }
+}
+
+impl Evaluate for Weights {
+    fn evaluate(&self, game: &Game) -> i32 {
+        let player = game.to_play();
+        let score = self.score(game, player) - self.score(game, player.other());
+        score.clamp(i64::from(i32::MIN), i64::from(i32::MAX)) as i32
+    }
+}
+
+// Scores every unfinished game 0, as the engine did before evaluation
+// was pluggable: the search then only tells wins and losses apart from
+// everything else.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blind;
+
+impl Evaluate for Blind {
+    fn evaluate(&self, _game: &Game) -> i32 {
+        0
+    }
+}
+
+#[derive(Debug)]
+pub enum WeightsError {
+    Io(io::Error),
+    // Lines count from 1.
+    BadLine(usize),
+    UnknownName { line: usize, name: String },
+    BadNumber { line: usize, text: String },
+}
+
+impl fmt::Display for WeightsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WeightsError::Io(error) => write!(f, "{}", error),
+            WeightsError::BadLine(line) => {
+                write!(f, "line {}: expected name = value", line)
+            }
+            WeightsError::UnknownName { line, name } => {
+                write!(f, "line {}: unknown weight {:?}", line, name)
+            }
+            WeightsError::BadNumber { line, text } => {
+                write!(f, "line {}: {:?} is not a number", line, text)
+            }
+        }
+    }
+}
+
+impl error::Error for WeightsError {}
+
+// Names not in the file keep their default values. # starts a comment.
+impl FromStr for Weights {
+    type Err = WeightsError;
+
+    fn from_str(text: &str) -> Result<Self, WeightsError> {
+        let mut weights = Weights::default();
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, value) = match line.find('=') {
+                Some(eq) => (line[..eq].trim(), line[eq + 1..].trim()),
+                None => return Err(WeightsError::BadLine(line_number)),
+            };
+            let numbers = value
+                .split_whitespace()
+                .map(|text| {
+                    text.parse::<i32>().map_err(|_| WeightsError::BadNumber {
+                        line: line_number,
+                        text: text.to_owned(),
+                    })
+                })
+                .collect::<Result<Vec<i32>, WeightsError>>()?;
+            let single = || match numbers[..] {
+                [n] => Ok(n),
+                _ => Err(WeightsError::BadNumber {
+                    line: line_number,
+                    text: value.to_owned(),
+                }),
+            };
+            match name {
+                "line" => weights.line = numbers.clone(),
+                "center" => weights.center = single()?,
+                "parity" => weights.parity = single()?,
+                _ => {
+                    return Err(WeightsError::UnknownName {
+                        line: line_number,
+                        name: name.to_owned(),
+                    })
+                }
+            }
+        }
+        Ok(weights)
+    }
+}
+
+impl fmt::Display for Weights {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let line: Vec<String> =
+            self.line.iter().map(|n| n.to_string()).collect();
+        writeln!(f, "line = {}", line.join(" "))?;
+        writeln!(f, "center = {}", self.center)?;
+        writeln!(f, "parity = {}", self.parity)
+    }
+}
+
+#[test]
+fn test_evaluate_empty_and_symmetric() {
+    let weights = Weights::default();
+    assert_eq!(weights.evaluate(&Game::with_config(GameConfig::standard())), 0);
+    // The same position from the other side scores the opposite, and a
+    // mirror image scores the same.
+    super::test_random_games(0x19, |game| {
+        if game.is_over() {
+            return;
+        }
+        let score = weights.evaluate(game);
+        assert_eq!(weights.evaluate(&game.mirrored()), score);
+        let x = weights.score(game, Player::X);
+        let o = weights.score(game, Player::O);
+        let expected = if game.to_play() == Player::X { x - o } else { o - x };
+        assert_eq!(i64::from(score), expected);
+    });
+}
+
+#[test]
+fn test_evaluate_terms() {
+    let center_only = Weights { line: Vec::new(), center: 1, parity: 0 };
+    // X in the center, O on the edge, X to play again.
+    let game = test_position(GameConfig::standard(), &[3, 0]);
+    assert_eq!(center_only.evaluate(&game), 1);
+
+    let lines_only =
+        Weights { line: vec![0, 1, 10, 100], center: 0, parity: 0 };
+    // X's one piece is in 7 blocks: 4 across, 1 up and 2 diagonal.
+    // It's O's turn, so that counts against them.
+    let game = test_position(GameConfig::standard(), &[3]);
+    assert_eq!(lines_only.evaluate(&game), -7);
+
+    // X's three in column 0 leave a threat on row 4: an even row, O's.
+    let parity_only = Weights { line: Vec::new(), center: 0, parity: 1 };
+    let game = test_position(GameConfig::standard(), &[0, 6, 0, 6, 0, 5]);
+    assert_eq!(parity_only.score(&game, Player::X), 0);
+    // The same threat a row higher is on X's row.
+    let game = test_position(GameConfig::standard(), &[6, 0, 0, 6, 0, 5, 0]);
+    assert_eq!(parity_only.score(&game, Player::X), 1);
+}
+
+#[test]
+fn test_evaluate_huge_weights() {
+    // Weights that parse fine can add up past i32: the score saturates
+    // rather than overflowing.
+    let huge: Weights = "line = 0 2000000000".parse().unwrap();
+    // X's piece in the center is in 7 blocks, O's in the corner in 3.
+    let game = test_position(GameConfig::standard(), &[3]);
+    assert_eq!(huge.evaluate(&game), i32::MIN);
+    let game = test_position(GameConfig::standard(), &[3, 0]);
+    assert_eq!(huge.evaluate(&game), i32::MAX);
+}
+
+#[test]
+fn test_weights_file() {
+    let weights = Weights { line: vec![0, 2, 8, 40], center: 5, parity: 12 };
+    assert_eq!(weights.to_string().parse::<Weights>().unwrap(), weights);
+
+    let text = "# tuned by hand\nparity = 7\n\ncenter=2  # more than before\n";
+    let parsed: Weights = text.parse().unwrap();
+    assert_eq!(parsed, Weights { center: 2, parity: 7, ..Weights::default() });
+
+    let path = std::env::temp_dir().join("connect_4_weights_test.txt");
+    weights.save(&path).unwrap();
+    assert_eq!(Weights::load(&path).unwrap(), weights);
+    fs::remove_file(&path).unwrap();
+
+    let error = "center 3".parse::<Weights>().unwrap_err();
+    assert_eq!(error.to_string(), "line 1: expected name = value");
+    let error = "\nbonus = 3".parse::<Weights>().unwrap_err();
+    assert_eq!(error.to_string(), "line 2: unknown weight \"bonus\"");
+    let error = "center = 3 4".parse::<Weights>().unwrap_err();
+    assert_eq!(error.to_string(), "line 1: \"3 4\" is not a number");
+    let error = "line = 0 one".parse::<Weights>().unwrap_err();
+    assert_eq!(error.to_string(), "line 1: \"one\" is not a number");
+    assert!(Weights::load("/nonexistent/weights.txt").is_err());
+}
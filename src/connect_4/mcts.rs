@@ -0,0 +1,267 @@
+/*
+    Monte Carlo Tree Search (MCTS).
+
+    Rather than looking at every move to a fixed depth like the engine,
+    MCTS plays lots of random games and grows a tree towards the moves that
+    win most often. Each iteration:
+    1. Selection: walk down the tree, picking children by UCT (a balance
+       between moves that have done well and moves we've barely tried).
+    2. Expansion: add one untried move as a new leaf.
+    3. Simulation: play random moves from there to the end of the game.
+    4. Backpropagation: record the result in every node on the path.
+    It needs no evaluation function, so it copes with big boards where
+    alpha-beta can't see far enough ahead. Like the engine, though, it
+    only plays the classic rules.
+*/
+
+use std::time::{Duration, Instant};
+
+use super::rng::Rng;
+use super::rules::ClassicOnly;
+use super::{Game, Player};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Budget {
+    Iterations(usize),
+    Time(Duration),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MctsResult {
+    // None if the game is already over.
+    pub best: Option<usize>,
+    // How often the best move was tried, and the fraction of those
+    // playouts it won (counting draws as half).
+    pub visits: u32,
+    pub win_rate: f64,
+    pub iterations: usize,
+}
+
+// The tree is stored as a Vec of nodes that refer to each other by index,
+// which avoids fighting the borrow checker over parent pointers.
+#[derive(Clone, Debug)]
+struct Node {
+    // The move that led here, and who made it.
+    col: Option<usize>,
+    mover: Player,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    untried: Vec<usize>,
+    visits: u32,
+    // Playouts won by mover, counting draws as half.
+    wins: f64,
+}
+
+impl Node {
+    fn new(
+        col: Option<usize>,
+        mover: Player,
+        parent: Option<usize>,
+        game: &Game,
+    ) -> Self {
+        let untried = if game.is_over() {
+            Vec::new()
+        } else {
+            game.valid_plays().collect()
+        };
+        Self {
+            col,
+            mover,
+            parent,
+            children: Vec::new(),
+            untried,
+            visits: 0,
+            wins: 0.0,
+        }
+    }
+
+    fn uct(&self, parent_visits: u32, exploration: f64) -> f64 {
+        let visits = f64::from(self.visits);
+        self.wins / visits
+            + exploration * (f64::from(parent_visits).ln() / visits).sqrt()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Mcts {
+    pub budget: Budget,
+    // The constant in UCT; larger explores more. sqrt(2) is the usual.
+    pub exploration: f64,
+    rng: Rng,
+}
+
+impl Mcts {
+    pub fn new(budget: Budget, seed: u64) -> Self {
+        Self { budget, exploration: 2f64.sqrt(), rng: Rng::new(seed) }
+    }
+
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = Rng::new(seed);
+    }
+
+    pub fn choose(
+        &mut self,
+        game: &Game,
+    ) -> Result<Option<usize>, ClassicOnly> {
+        Ok(self.search(game)?.best)
+    }
+
+    pub fn search(&mut self, game: &Game) -> Result<MctsResult, ClassicOnly> {
+        game.require_classic()?;
+        let start = Instant::now();
+        let mut tree =
+            vec![Node::new(None, game.to_play().other(), None, game)];
+        let mut iterations = 0;
+        if !game.is_over() {
+            // Always do at least one iteration, so there is a move to return.
+            while iterations == 0 || !self.out_of_budget(iterations, start) {
+                self.iterate(&mut tree, game);
+                iterations += 1;
+            }
+        }
+
+        let best =
+            tree[0].children.iter().max_by_key(|&&child| tree[child].visits);
+        Ok(match best {
+            Some(&child) => MctsResult {
+                best: tree[child].col,
+                visits: tree[child].visits,
+                win_rate: tree[child].wins / f64::from(tree[child].visits),
+                iterations,
+            },
+            None => {
+                MctsResult { best: None, visits: 0, win_rate: 0.0, iterations }
+            }
+        })
+    }
+
+    fn out_of_budget(&self, iterations: usize, start: Instant) -> bool {
+        match self.budget {
+            Budget::Iterations(limit) => iterations >= limit,
+            Budget::Time(limit) => start.elapsed() >= limit,
+        }
+    }
+
+    fn iterate(&mut self, tree: &mut Vec<Node>, root: &Game) {
+        let mut game = root.clone();
+        let mut node = 0;
+
+        // Selection
+        while tree[node].untried.is_empty() && !tree[node].children.is_empty() {
+            let parent_visits = tree[node].visits;
+            let exploration = self.exploration;
+            node = *tree[node]
+                .children
+                .iter()
+                .max_by(|&&a, &&b| {
+                    let a = tree[a].uct(parent_visits, exploration);
+                    let b = tree[b].uct(parent_visits, exploration);
+                    a.partial_cmp(&b).unwrap()
+                })
+                .unwrap();
+            game.make_move(tree[node].col.unwrap()).unwrap();
+        }
+
+        // Expansion
+        if !tree[node].untried.is_empty() {
+            let index = self.rng.below(tree[node].untried.len());
+            let col = tree[node].untried.swap_remove(index);
+            let mover = game.to_play();
+            game.make_move(col).unwrap();
+            tree.push(Node::new(Some(col), mover, Some(node), &game));
+            let child = tree.len() - 1;
+            tree[node].children.push(child);
+            node = child;
+        }
+
+        // Simulation
+        let winner = self.playout(&mut game);
+
+        // Backpropagation
+        let mut current = Some(node);
+        while let Some(index) = current {
+            let node = &mut tree[index];
+            node.visits += 1;
+            node.wins += match winner {
+                Some(player) if player == node.mover => 1.0,
+                Some(_) => 0.0,
+                None => 0.5,
+            };
+            current = node.parent;
+        }
+    }
+
+    // Plays uniformly random moves until the game ends; returns the winner.
+    fn playout(&mut self, game: &mut Game) -> Option<Player> {
+        let mut plays = Vec::new();
+        while !game.is_over() {
+            plays.clear();
+            plays.extend(game.valid_plays());
+            let col = self.rng.choose(&plays).unwrap();
+            game.make_move(col).unwrap();
+        }
+        game.winner()
+    }
+}
+
+#[test]
+fn test_mcts_reproducible() {
+    let game = Game::with_config(super::GameConfig::standard());
+    let first = Mcts::new(Budget::Iterations(300), 1).search(&game).unwrap();
+    let second = Mcts::new(Budget::Iterations(300), 1).search(&game).unwrap();
+    assert_eq!(first, second);
+    assert_eq!(first.iterations, 300);
+}
+
+#[test]
+fn test_mcts_wins_and_blocks() {
+    let mut game = super::test_position(
+        super::GameConfig::standard(),
+        &[0, 0, 1, 1, 2, 2],
+    );
+    let mut mcts = Mcts::new(Budget::Iterations(1000), 2);
+    assert_eq!(mcts.choose(&game).unwrap(), Some(3));
+    // O to move, and X threatens to win in column 3.
+    game.undo();
+    assert_eq!(mcts.choose(&game).unwrap(), Some(3));
+}
+
+#[test]
+fn test_mcts_time_budget() {
+    let game = Game::new();
+    let result = Mcts::new(Budget::Time(Duration::from_millis(20)), 3)
+        .search(&game)
+        .unwrap();
+    assert!(result.iterations > 0);
+    assert!(result.best.is_some());
+}
+
+#[test]
+fn test_mcts_game_over() {
+    let game = super::test_position(
+        super::GameConfig::default(),
+        &[0, 0, 1, 1, 2, 2, 3],
+    );
+    let result = Mcts::new(Budget::Iterations(100), 4).search(&game).unwrap();
+    assert_eq!(result.best, None);
+    assert_eq!(result.iterations, 0);
+}
+
+#[test]
+fn test_mcts_beats_random() {
+    let mut rng = Rng::new(5);
+    for seed in 0..4 {
+        let mut mcts = Mcts::new(Budget::Iterations(400), seed);
+        let mut game = Game::with_config(super::GameConfig::standard());
+        while !game.is_over() {
+            let col = if game.to_play() == Player::X {
+                mcts.choose(&game).unwrap().unwrap()
+            } else {
+                let plays: Vec<usize> = game.valid_plays().collect();
+                rng.choose(&plays).unwrap()
+            };
+            game.make_move(col).unwrap();
+        }
+        assert_eq!(game.winner(), Some(Player::X));
+    }
+}
@@ -0,0 +1,395 @@
+/*
+    Reading games from text.
+
+    Two formats are understood:
+    - Move sequences: the columns played, numbered from 1, e.g. "4453".
+      On boards more than 9 wide, separate the moves with spaces or commas:
+      "4 4 10 3".
+    - Diagrams: the board drawn top row first, like Display prints it.
+      Walls, the floor, column numbers and the status line are optional.
+      A highlighted winning line reads as the winner's pieces.
+
+        | . . . . |
+        | . X . . |
+        | X O O . |
+
+    FromStr is the trait behind str::parse, so both of these work:
+        let game: Game = "4453".parse()?;
+        let game = "4453".parse::<Game>()?;
+*/
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(test)]
+use super::rules::Move;
+use super::{BoardError, Game, GameConfig, Player, CONNECT};
+
+// Lines and columns of the text count from 1, as in an editor; so do
+// board columns and rows (from the bottom), as in the Display output.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ParseError {
+    Empty,
+    UnexpectedChar { line: usize, column: usize, found: char },
+    RaggedRow { line: usize, expected: usize, found: usize },
+    FloatingPiece { col: usize, row: usize },
+    // X moves first, so X has as many pieces as O or one more.
+    PieceCount { x: usize, o: usize },
+    TwoWinners,
+    // The winner has to be whoever moved last.
+    WinnerOutOfTurn(Player),
+    BadMove { number: usize, text: String },
+    IllegalMove { number: usize, col: usize, error: BoardError },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "no board found"),
+            ParseError::UnexpectedChar { line, column, found } => write!(
+                f,
+                "line {}, column {}: unexpected {:?}",
+                line, column, found
+            ),
+            ParseError::RaggedRow { line, expected, found } => write!(
+                f,
+                "line {}: expected {} cells, found {}",
+                line, expected, found
+            ),
+            ParseError::FloatingPiece { col, row } => write!(
+                f,
+                "column {}, row {}: piece with nothing underneath",
+                col, row
+            ),
+            ParseError::PieceCount { x, o } => {
+                write!(f, "{} X pieces and {} O pieces can't happen", x, o)
+            }
+            ParseError::TwoWinners => write!(f, "both players have won"),
+            ParseError::WinnerOutOfTurn(player) => {
+                write!(f, "{:?} has won, but didn't move last", player)
+            }
+            ParseError::BadMove { number, text } => {
+                write!(f, "move {}: {:?} is not a column number", number, text)
+            }
+            ParseError::IllegalMove { number, col, error } => {
+                write!(f, "move {} (column {}): {}", number, col, error)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+impl Game {
+    // Plays a sequence of 1-based columns on an empty board.
+    pub fn from_moves(
+        config: GameConfig,
+        text: &str,
+    ) -> Result<Self, ParseError> {
+        // Spaces around the moves don't separate anything.
+        let text = text.trim();
+        let separated = text.contains(|c: char| c.is_whitespace() || c == ',');
+        let tokens: Vec<String> = if separated {
+            text.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|token| !token.is_empty())
+                .map(|token| token.to_owned())
+                .collect()
+        } else {
+            text.chars().map(|c| c.to_string()).collect()
+        };
+        let mut game = Game::with_config(config);
+        for (i, token) in tokens.into_iter().enumerate() {
+            let number = i + 1;
+            let col = match token.parse::<usize>() {
+                Ok(col) if col > 0 => col - 1,
+                _ => return Err(ParseError::BadMove { number, text: token }),
+            };
+            if let Err(error) = game.make_move(col) {
+                return Err(ParseError::IllegalMove {
+                    number,
+                    col: col + 1,
+                    error,
+                });
+            }
+        }
+        Ok(game)
+    }
+
+    // Reads a board diagram; its size comes from the diagram itself.
+    pub fn from_diagram(
+        text: &str,
+        connect: usize,
+    ) -> Result<Self, ParseError> {
+        // rows[0] is the top row, as written.
+        let mut rows: Vec<Vec<Option<Player>>> = Vec::new();
+        // ASCII highlights both players' lines with *, so whose they are
+        // is worked out from the piece counts below. Until then they're
+        // read as X's.
+        let mut stars = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            // The floor: everything after it is numbers and status.
+            if trimmed.starts_with('+') || trimmed.starts_with('└') {
+                break;
+            }
+            let mut row = Vec::new();
+            for (j, c) in line.chars().enumerate() {
+                match c {
+                    '.' | '·' => row.push(None),
+                    'X' | 'x' | '●' | '◉' => row.push(Some(Player::X)),
+                    'O' | 'o' | '○' | '◎' => row.push(Some(Player::O)),
+                    '*' => {
+                        stars.push((rows.len(), row.len()));
+                        row.push(Some(Player::X));
+                    }
+                    '|' | '│' => {}
+                    c if c.is_whitespace() => {}
+                    found => {
+                        let (line, column) = (i + 1, j + 1);
+                        return Err(ParseError::UnexpectedChar {
+                            line,
+                            column,
+                            found,
+                        });
+                    }
+                }
+            }
+            if let Some(first) = rows.first() {
+                if row.len() != first.len() {
+                    return Err(ParseError::RaggedRow {
+                        line: i + 1,
+                        expected: first.len(),
+                        found: row.len(),
+                    });
+                }
+            }
+            rows.push(row);
+        }
+        if rows.is_empty() || rows[0].is_empty() {
+            return Err(ParseError::Empty);
+        }
+
+        // The winner moved last, so with their line counted they have one
+        // piece more than the other player (X) or the same number (O).
+        if !stars.is_empty() {
+            let count = |player| {
+                let cells = rows.iter().flatten();
+                cells.filter(|&&cell| cell == Some(player)).count()
+            };
+            let o = count(Player::O);
+            let x = count(Player::X) - stars.len();
+            if o + stars.len() == x {
+                for &(row, col) in &stars {
+                    rows[row][col] = Some(Player::O);
+                }
+            }
+        }
+
+        let config = GameConfig {
+            connect,
+            ..GameConfig::new(rows[0].len(), rows.len())
+        };
+        let mut board = vec![Vec::new(); config.width];
+        let (mut x, mut o) = (0, 0);
+        // Bottom row first, so each column fills up from the bottom.
+        for (row, cells) in rows.iter().rev().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                match cell {
+                    None => {}
+                    Some(_) if board[col].len() < row => {
+                        return Err(ParseError::FloatingPiece {
+                            col: col + 1,
+                            row: row + 1,
+                        });
+                    }
+                    Some(player) => {
+                        match player {
+                            Player::X => x += 1,
+                            Player::O => o += 1,
+                        }
+                        board[col].push(player);
+                    }
+                }
+            }
+        }
+        let to_play = if x == o {
+            Player::X
+        } else if x == o + 1 {
+            Player::O
+        } else {
+            return Err(ParseError::PieceCount { x, o });
+        };
+
+        let game = Game::from_columns(config, board, to_play);
+        let has_line = |player| {
+            game.blocks().any(|blck| {
+                blck.iter().all(|&(i, j)| game.get(i, j) == Some(player))
+            })
+        };
+        match (has_line(Player::X), has_line(Player::O)) {
+            (true, true) => Err(ParseError::TwoWinners),
+            // The player who moved last is the one not to play.
+            (true, false) if to_play == Player::X => {
+                Err(ParseError::WinnerOutOfTurn(Player::X))
+            }
+            (false, true) if to_play == Player::O => {
+                Err(ParseError::WinnerOutOfTurn(Player::O))
+            }
+            _ => Ok(game),
+        }
+    }
+}
+
+impl FromStr for Game {
+    type Err = ParseError;
+
+    // Anything with cells or walls in it is a diagram, and so are blank
+    // lines with no moves on them; otherwise it's a move sequence on the
+    // default board.
+    fn from_str(text: &str) -> Result<Self, ParseError> {
+        let drawn = text.contains(|c| ".·|│XxOo●○◉◎*".contains(c));
+        let blank_page =
+            text.contains('\n') && !text.contains(|c: char| c.is_ascii_digit());
+        if drawn || blank_page {
+            Game::from_diagram(text, CONNECT)
+        } else {
+            Game::from_moves(GameConfig::default(), text)
+        }
+    }
+}
+
+#[test]
+fn test_parse_moves() {
+    let game: Game = "4453".parse().unwrap();
+    assert_eq!(
+        game.history(),
+        &[
+            (Move::Drop(3), Player::X),
+            (Move::Drop(3), Player::O),
+            (Move::Drop(4), Player::X),
+            (Move::Drop(2), Player::O)
+        ]
+    );
+    let spaced = Game::from_moves(GameConfig::default(), "4, 4 5,3").unwrap();
+    assert_eq!(spaced, game);
+    let wide = Game::from_moves(GameConfig::default(), "10 10 1").unwrap();
+    assert_eq!(wide.get(9, 1), Some(Player::O));
+    assert_eq!("".parse::<Game>().unwrap(), Game::new());
+    // A trailing newline doesn't make it a diagram.
+    assert_eq!("4453\n".parse::<Game>(), Ok(game));
+    let lines = Game::from_moves(GameConfig::default(), "4 4\n5 3").unwrap();
+    assert_eq!("4 4\n5 3\n".parse::<Game>(), Ok(lines));
+}
+
+#[test]
+fn test_parse_move_errors() {
+    assert_eq!(
+        "44a3".parse::<Game>(),
+        Err(ParseError::BadMove { number: 3, text: "a".to_owned() })
+    );
+    assert_eq!(
+        "440".parse::<Game>(),
+        Err(ParseError::BadMove { number: 3, text: "0".to_owned() })
+    );
+    let config = GameConfig::standard();
+    assert_eq!(
+        Game::from_moves(config, "1111111"),
+        Err(ParseError::IllegalMove {
+            number: 7,
+            col: 1,
+            error: BoardError::ColumnFull(0)
+        })
+    );
+    assert_eq!(
+        Game::from_moves(config, "8"),
+        Err(ParseError::IllegalMove {
+            number: 1,
+            col: 8,
+            error: BoardError::ColumnOutOfRange(7)
+        })
+    );
+    assert_eq!(
+        Game::from_moves(config, "12121212"),
+        Err(ParseError::IllegalMove {
+            number: 8,
+            col: 2,
+            error: BoardError::GameOver
+        })
+    );
+}
+
+#[test]
+fn test_parse_diagram() {
+    let game: Game = "
+        . . . .
+        . X . .
+        X O O .
+    "
+    .parse()
+    .unwrap();
+    assert_eq!((game.width(), game.height()), (4, 3));
+    assert_eq!(game.get(0, 0), Some(Player::X));
+    assert_eq!(game.get(1, 1), Some(Player::X));
+    assert_eq!(game.get(2, 0), Some(Player::O));
+    assert_eq!(game.get(3, 0), None);
+    assert_eq!(game.to_play(), Player::X);
+}
+
+#[test]
+fn test_parse_display_output() {
+    use super::display::{RenderOptions, Style};
+    // Whatever Display prints, we can read back.
+    super::test_random_games(0x9a, |game| {
+        if game.config().connect != CONNECT {
+            return;
+        }
+        let parsed: Game = format!("{}", game).parse().unwrap();
+        assert_eq!(&parsed, game);
+        for &style in &[Style::Ascii, Style::Unicode] {
+            for &highlight_win in &[false, true] {
+                let options = RenderOptions { style, highlight_win };
+                let text = format!("{}", game.render(options));
+                let parsed = Game::from_diagram(&text, CONNECT);
+                assert_eq!(parsed.as_ref(), Ok(game));
+            }
+        }
+    });
+}
+
+#[test]
+fn test_parse_diagram_errors() {
+    assert_eq!("\n\n".parse::<Game>(), Err(ParseError::Empty));
+    assert_eq!(
+        ". . .\n. Z .\n".parse::<Game>(),
+        Err(ParseError::UnexpectedChar { line: 2, column: 3, found: 'Z' })
+    );
+    assert_eq!(
+        ". . .\n. . . .\n".parse::<Game>(),
+        Err(ParseError::RaggedRow { line: 2, expected: 3, found: 4 })
+    );
+    assert_eq!(
+        ". X .\n. . .\nO X .".parse::<Game>(),
+        Err(ParseError::FloatingPiece { col: 2, row: 3 })
+    );
+    assert_eq!(
+        ". . .\nX X .".parse::<Game>(),
+        Err(ParseError::PieceCount { x: 2, o: 0 })
+    );
+    assert_eq!(
+        "X O\nX O\nX O\nX O".parse::<Game>(),
+        Err(ParseError::TwoWinners)
+    );
+    assert_eq!(
+        "X . .\nX O .\nO O .\nX O X\nX O X".parse::<Game>(),
+        Err(ParseError::WinnerOutOfTurn(Player::O))
+    );
+    let error = ". X .\n. . .\nO X .".parse::<Game>().unwrap_err();
+    assert_eq!(
+        format!("{}", error),
+        "column 2, row 3: piece with nothing underneath"
+    );
+}
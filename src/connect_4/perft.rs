@@ -0,0 +1,181 @@
+/*
+    Perft: counting every line of play to a fixed depth.
+
+    The name comes from chess programming ("performance test"). Starting
+    from a position, we play every legal move, then every reply, and so
+    on, and count the positions we end up at. A won game is a leaf: play
+    stops there, so it counts once, however shallow. So is a full board.
+
+    The counts depend on nothing but the rules, so if valid_plays(), play()
+    and winner() agree with each other and with the rules, they match
+    numbers worked out independently. A move generator that forgets a
+    column, lets one overflow, or misses a win shows up as a wrong count,
+    and divide() narrows it down to the move where it goes wrong.
+
+    Only the classic rules are counted: pops can go on forever, so under
+    the variants in rules.rs the counts would mean something else.
+*/
+
+#[cfg(test)]
+use std::convert::TryFrom;
+
+use super::rules::ClassicOnly;
+use super::Game;
+
+impl Game {
+    // The number of positions reached by playing exactly depth moves,
+    // plus the games that end sooner.
+    pub fn perft(&self, depth: usize) -> Result<u64, ClassicOnly> {
+        self.require_classic()?;
+        Ok(count(&mut self.clone(), depth))
+    }
+
+    // perft(depth), split by first move: (column, count) for each legal
+    // one. Comparing these against a trusted version shows which move
+    // leads to the difference.
+    pub fn divide(
+        &self,
+        depth: usize,
+    ) -> Result<Vec<(usize, u64)>, ClassicOnly> {
+        self.require_classic()?;
+        if depth == 0 || self.winner().is_some() {
+            return Ok(Vec::new());
+        }
+        let mut game = self.clone();
+        let plays: Vec<usize> = game.valid_plays().collect();
+        Ok(plays
+            .into_iter()
+            .map(|col| {
+                let player = game.to_play();
+                game.play(col, player);
+                let nodes = count(&mut game, depth - 1);
+                game.undo();
+                (col, nodes)
+            })
+            .collect())
+    }
+}
+
+// Plays and takes back moves on one game rather than cloning it at each
+// step.
+fn count(game: &mut Game, depth: usize) -> u64 {
+    if depth == 0 || game.winner().is_some() {
+        return 1;
+    }
+    let plays: Vec<usize> = game.valid_plays().collect();
+    if plays.is_empty() {
+        return 1;
+    }
+    let mut nodes = 0;
+    for col in plays {
+        let player = game.to_play();
+        game.play(col, player);
+        nodes += count(game, depth - 1);
+        game.undo();
+    }
+    nodes
+}
+
+#[cfg(test)]
+fn bitboard_perft(board: super::bitboard::BitBoard, depth: usize) -> u64 {
+    if depth == 0 || board.is_over() {
+        return 1;
+    }
+    board
+        .valid_plays()
+        .map(|col| {
+            let mut next = board;
+            next.make_move(col).unwrap();
+            bitboard_perft(next, depth - 1)
+        })
+        .sum()
+}
+
+// Counts from the empty board, worked out with both Game and BitBoard.
+// Up to 6 plies nobody can have won yet, so these are just powers of the
+// width less the lines that overfill a column. The tests only go as deep
+// as runs quickly in a debug build, which on these boards is before
+// anyone can have won.
+#[cfg(test)]
+const STANDARD: [u64; 10] =
+    [1, 7, 49, 343, 2401, 16807, 117649, 823536, 5686266, 39452034];
+#[cfg(test)]
+const DEFAULT: [u64; 10] =
+    [1, 10, 100, 1000, 10000, 100000, 999990, 9999360, 99148932, 985122288];
+// Three in a row on 4x3, played out to the end, so wins and full boards
+// both cut lines short. Counted by a separate brute-force program. After
+// 12 plies every game is over and the count stays put.
+#[cfg(test)]
+const SMALL: [u64; 14] = [
+    1, 4, 16, 64, 252, 960, 3204, 10192, 26876, 61670, 103404, 133656, 133656,
+    133656,
+];
+
+#[test]
+fn test_perft_known_counts() {
+    let game = Game::with_config(super::GameConfig::standard());
+    for (depth, &expected) in STANDARD.iter().enumerate().take(8) {
+        assert_eq!(
+            game.perft(depth).unwrap(),
+            expected,
+            "7x6, depth {}",
+            depth
+        );
+    }
+    let game = Game::with_config(super::GameConfig::default());
+    for (depth, &expected) in DEFAULT.iter().enumerate().take(7) {
+        assert_eq!(
+            game.perft(depth).unwrap(),
+            expected,
+            "10x5, depth {}",
+            depth
+        );
+    }
+    let config =
+        super::GameConfig { connect: 3, ..super::GameConfig::new(4, 3) };
+    let game = Game::with_config(config);
+    let board = super::bitboard::BitBoard::try_from(&game).unwrap();
+    for (depth, &expected) in SMALL.iter().enumerate() {
+        assert_eq!(
+            game.perft(depth).unwrap(),
+            expected,
+            "4x3, depth {}",
+            depth
+        );
+        assert_eq!(bitboard_perft(board, depth), expected);
+    }
+}
+
+#[test]
+fn test_perft_matches_bitboard() {
+    let mut checked = 0;
+    super::test_random_games(0x24, |game| {
+        checked += 1;
+        if checked % 25 != 0 {
+            return;
+        }
+        let board = super::bitboard::BitBoard::try_from(game).unwrap();
+        assert_eq!(game.perft(4).unwrap(), bitboard_perft(board, 4));
+    });
+}
+
+#[test]
+fn test_perft_leaves_and_divide() {
+    // X has just won: play stops, so it's a single leaf at any depth.
+    let mut game = Game::with_config(super::GameConfig::standard());
+    for &col in &[0, 1, 0, 1, 0, 1, 0] {
+        game.make_move(col).unwrap();
+    }
+    assert_eq!(game.perft(3).unwrap(), 1);
+    assert!(game.divide(3).unwrap().is_empty());
+
+    // The first moves split the count between them, in column order.
+    let game = Game::with_config(super::GameConfig::standard());
+    let divided = game.divide(5).unwrap();
+    assert_eq!(divided.len(), 7);
+    assert_eq!(divided[0], (0, STANDARD[4]));
+    let total: u64 = divided.iter().map(|&(_, nodes)| nodes).sum();
+    assert_eq!(total, STANDARD[5]);
+    // Perft doesn't change the game it's given.
+    assert_eq!(game.move_count(), 0);
+}
@@ -0,0 +1,246 @@
+/*
+    Playing a game in the terminal.
+
+    Each side is either a person typing column numbers or one of the
+    agents. The input and output are generic (any BufRead and any Write),
+    so main passes stdin and stdout while the tests pass strings.
+*/
+
+use std::io::{self, BufRead, Write};
+use std::rc::Rc;
+
+use super::agent::{
+    Agent, BookAgent, FirstLegalAgent, MatchOutcome, MctsAgent, RandomAgent,
+    SearchAgent,
+};
+use super::book::Book;
+use super::mcts::Budget;
+use super::{Game, GameConfig, Player};
+
+pub enum Seat {
+    Human,
+    Computer(Box<dyn Agent>),
+}
+
+impl Seat {
+    // "human", "random", "first-legal", "search" or "search-N" (depth N),
+    // "mcts" or "mcts-N" (N playouts per move). N is at least 1.
+    pub fn parse(name: &str, seed: u64) -> Option<Seat> {
+        let (kind, number) = split_name(name);
+        let agent: Box<dyn Agent> = match (kind, number) {
+            (_, Some(0)) => return None,
+            ("human", None) => return Some(Seat::Human),
+            ("random", None) => Box::new(RandomAgent::new(seed)),
+            ("first-legal", None) => Box::new(FirstLegalAgent),
+            ("search", depth) => Box::new(SearchAgent::new(depth.unwrap_or(6))),
+            ("mcts", playouts) => {
+                let budget = Budget::Iterations(playouts.unwrap_or(5000));
+                Box::new(MctsAgent::new(budget, seed))
+            }
+            _ => return None,
+        };
+        Some(Seat::Computer(agent))
+    }
+
+    // Like parse, but "search" players open from the book.
+    pub fn parse_with_book(
+        name: &str,
+        seed: u64,
+        book: &Rc<Book>,
+    ) -> Option<Seat> {
+        match split_name(name) {
+            ("search", depth) if depth != Some(0) => {
+                let depth = depth.unwrap_or(6);
+                let agent = BookAgent::new(Rc::clone(book), depth);
+                Some(Seat::Computer(Box::new(agent)))
+            }
+            _ => Seat::parse(name, seed),
+        }
+    }
+}
+
+// "search-4" is ("search", Some(4)); "first-legal" is just a name.
+fn split_name(name: &str) -> (&str, Option<usize>) {
+    match name.rfind('-') {
+        Some(i) => match name[i + 1..].parse() {
+            Ok(number) => (&name[..i], Some(number)),
+            Err(_) => (name, None),
+        },
+        None => (name, None),
+    }
+}
+
+// Plays one game, showing the board after every move. Returns None if
+// a human quit (or the input ran out) before the end.
+pub fn play<R: BufRead, W: Write>(
+    mut input: R,
+    mut output: W,
+    config: GameConfig,
+    x: &mut Seat,
+    o: &mut Seat,
+) -> io::Result<Option<MatchOutcome>> {
+    let mut game = Game::with_config(config);
+    writeln!(output, "{}\n", game)?;
+    while !game.is_over() {
+        let player = game.to_play();
+        let seat = match player {
+            Player::X => &mut *x,
+            Player::O => &mut *o,
+        };
+        let col = match seat {
+            Seat::Human => match ask(&mut input, &mut output, &game)? {
+                Some(col) => col,
+                None => return Ok(None),
+            },
+            Seat::Computer(agent) => {
+                let col = agent.choose(&game);
+                writeln!(
+                    output,
+                    "{:?} ({}) plays column {}",
+                    player,
+                    agent.name(),
+                    col + 1
+                )?;
+                col
+            }
+        };
+        if game.make_move(col).is_err() {
+            writeln!(
+                output,
+                "That's not a legal move: {:?} forfeits.",
+                player
+            )?;
+            return Ok(Some(MatchOutcome::Forfeit { player, col }));
+        }
+        writeln!(output, "{}\n", game)?;
+    }
+    match game.winner() {
+        Some(player) => {
+            writeln!(output, "{:?} wins!", player)?;
+            Ok(Some(MatchOutcome::Won(player)))
+        }
+        None => {
+            writeln!(output, "It's a draw.")?;
+            Ok(Some(MatchOutcome::Draw))
+        }
+    }
+}
+
+// Asks until we get a column that can be played; None means quit.
+fn ask<R: BufRead, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    game: &Game,
+) -> io::Result<Option<usize>> {
+    let width = game.width();
+    loop {
+        write!(
+            output,
+            "{:?}, pick a column (1-{}, q to quit): ",
+            game.to_play(),
+            width
+        )?;
+        // Without this the prompt can sit in a buffer while we wait.
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim();
+        if line == "q" {
+            return Ok(None);
+        }
+        match line.parse::<usize>() {
+            Ok(col) if col >= 1 && col <= width => {
+                if game.playable(col - 1) {
+                    return Ok(Some(col - 1));
+                }
+                writeln!(output, "Column {} is full.", col)?;
+            }
+            _ => writeln!(
+                output,
+                "{:?} is not a column from 1 to {}.",
+                line, width
+            )?,
+        }
+    }
+}
+
+#[cfg(test)]
+fn test_play(
+    input: &str,
+    x: &mut Seat,
+    o: &mut Seat,
+) -> (Option<MatchOutcome>, String) {
+    let mut output = Vec::new();
+    let config = GameConfig::new(4, 4);
+    let outcome = play(input.as_bytes(), &mut output, config, x, o).unwrap();
+    (outcome, String::from_utf8(output).unwrap())
+}
+
+#[test]
+fn test_human_vs_human() {
+    let input = "1\n2\n1\n2\n1\n2\n1\n";
+    let (outcome, output) =
+        test_play(input, &mut Seat::Human, &mut Seat::Human);
+    assert_eq!(outcome, Some(MatchOutcome::Won(Player::X)));
+    assert!(output.contains("X, pick a column (1-4, q to quit): "));
+    assert!(output.ends_with("X wins!\n"));
+    // The board is shown before the first move and after every move.
+    assert_eq!(output.matches("+---------+").count(), 8);
+}
+
+#[test]
+fn test_human_reprompted() {
+    let input = "\n0\n5\nfour\n1\n1\n1\n1\n1\nq\n";
+    let (outcome, output) =
+        test_play(input, &mut Seat::Human, &mut Seat::Human);
+    assert_eq!(outcome, None);
+    assert!(output.contains("\"\" is not a column from 1 to 4."));
+    assert!(output.contains("\"0\" is not a column from 1 to 4."));
+    assert!(output.contains("\"5\" is not a column from 1 to 4."));
+    assert!(output.contains("\"four\" is not a column from 1 to 4."));
+    assert!(output.contains("Column 1 is full."));
+    // Running out of input is the same as quitting.
+    let (outcome, _) = test_play("1\n", &mut Seat::Human, &mut Seat::Human);
+    assert_eq!(outcome, None);
+}
+
+#[test]
+fn test_human_vs_computer() {
+    let mut computer = Seat::parse("first-legal", 0).unwrap();
+    let input = "2\n3\n2\n3\n";
+    let (outcome, output) = test_play(input, &mut Seat::Human, &mut computer);
+    assert!(output.contains("O (first-legal) plays column 1"));
+    assert_eq!(outcome, Some(MatchOutcome::Won(Player::O)));
+    assert!(output.ends_with("O wins!\n"));
+}
+
+#[test]
+fn test_parse_seat() {
+    let name = |text| match Seat::parse(text, 0) {
+        Some(Seat::Computer(agent)) => Some(agent.name()),
+        Some(Seat::Human) => Some("human".to_owned()),
+        None => None,
+    };
+    assert_eq!(name("human"), Some("human".to_owned()));
+    assert_eq!(name("search"), Some("search-6".to_owned()));
+    assert_eq!(name("search-3"), Some("search-3".to_owned()));
+    assert_eq!(name("mcts-200"), Some("mcts-200".to_owned()));
+    assert_eq!(name("random"), Some("random".to_owned()));
+    assert_eq!(name("first-legal"), Some("first-legal".to_owned()));
+    assert_eq!(name("random-3"), None);
+    assert_eq!(name("search-0"), None);
+    assert_eq!(name("mcts-0"), None);
+    assert_eq!(name("robot"), None);
+
+    let book = Rc::new(Book::generate(GameConfig::new(4, 4), 1, 2).unwrap());
+    match Seat::parse_with_book("search-3", 0, &book) {
+        Some(Seat::Computer(agent)) => {
+            assert_eq!(agent.name(), "book+search-3")
+        }
+        _ => panic!("expected a computer player"),
+    }
+    assert!(Seat::parse_with_book("random", 0, &book).is_some());
+    assert!(Seat::parse_with_book("search-0", 0, &book).is_none());
+}
@@ -0,0 +1,519 @@
+/*
+    Game records: saving games as text, and reading them back.
+
+    The format is modelled on PGN, which chess players use to share games.
+    A few tag lines describe the game, then come the moves, numbered in
+    pairs (X's move, then O's), with columns counted from 1 and the result
+    at the end:
+
+        [X "Alice"]
+        [O "search-4"]
+        [Date "2026.10.17"]
+        [Width "7"]
+        [Height "6"]
+        [Connect "4"]
+        [Result "1-0"]
+
+        1. 4 4 2. 5 5 3. 6 6 4. 3 1-0
+
+    Games under other rules (rules.rs) have a Rules tag after Connect,
+    and write pops as "p" and the column, e.g. "p4".
+
+    Reading a record replays the moves, so a record with an illegal move,
+    or a result that doesn't match the board, is rejected.
+*/
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+use super::agent::{MatchOutcome, MatchResult};
+use super::rules::{Move, Rules};
+use super::{BoardError, Game, GameConfig, Player};
+
+// The largest number a Width, Height or Connect tag can hold, as in the
+// book and solver files. Without a limit, a record could ask for a board
+// too big to allocate.
+const MAX_SIZE: usize = u8::MAX as usize;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GameResult {
+    Won(Player),
+    Draw,
+    // Still going, or stopped without a result.
+    Unfinished,
+}
+
+impl GameResult {
+    // What the board says; a game can also end early, e.g. by forfeit.
+    pub fn of(game: &Game) -> Self {
+        match game.winner() {
+            Some(player) => GameResult::Won(player),
+            None if game.is_over() => GameResult::Draw,
+            None => GameResult::Unfinished,
+        }
+    }
+
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "1-0" => Some(GameResult::Won(Player::X)),
+            "0-1" => Some(GameResult::Won(Player::O)),
+            "1/2-1/2" => Some(GameResult::Draw),
+            "*" => Some(GameResult::Unfinished),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameResult::Won(Player::X) => write!(f, "1-0"),
+            GameResult::Won(Player::O) => write!(f, "0-1"),
+            GameResult::Draw => write!(f, "1/2-1/2"),
+            GameResult::Unfinished => write!(f, "*"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct GameRecord {
+    pub x: String,
+    pub o: String,
+    // YYYY.MM.DD, with ?s for unknown parts as in PGN.
+    pub date: String,
+    pub result: GameResult,
+    // Any other tags, in the order they were read.
+    pub tags: Vec<(String, String)>,
+    pub game: Game,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RecordError {
+    BadTag { line: usize },
+    BadValue { tag: String, value: String },
+    BadToken(String),
+    MoveNumber { expected: usize, found: String },
+    IllegalMove { number: usize, col: usize, error: BoardError },
+    // The moves end without a result, or the Result tag disagrees with it.
+    MissingResult,
+    ResultMismatch { tag: GameResult, moves: GameResult },
+    // The claimed result isn't what happened on the board.
+    WrongResult { claimed: GameResult, actual: GameResult },
+    TrailingText(String),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RecordError::BadTag { line } => {
+                write!(f, "line {}: expected [Name \"value\"]", line)
+            }
+            RecordError::BadValue { tag, value } => {
+                write!(f, "tag {}: bad value {:?}", tag, value)
+            }
+            RecordError::BadToken(token) => {
+                write!(f, "{:?} is not a move", token)
+            }
+            RecordError::MoveNumber { expected, found } => {
+                write!(
+                    f,
+                    "expected move number {}., found {:?}",
+                    expected, found
+                )
+            }
+            RecordError::IllegalMove { number, col, error } => {
+                write!(f, "move {} (column {}): {}", number, col, error)
+            }
+            RecordError::MissingResult => write!(f, "the moves have no result"),
+            RecordError::ResultMismatch { tag, moves } => write!(
+                f,
+                "the Result tag says {}, but the moves end in {}",
+                tag, moves
+            ),
+            RecordError::WrongResult { claimed, actual } => write!(
+                f,
+                "the record says {}, but the board says {}",
+                claimed, actual
+            ),
+            RecordError::TrailingText(text) => {
+                write!(f, "unexpected {:?} after the result", text)
+            }
+        }
+    }
+}
+
+impl error::Error for RecordError {}
+
+impl GameRecord {
+    pub fn new(x: &str, o: &str, game: Game) -> Self {
+        Self {
+            x: x.to_owned(),
+            o: o.to_owned(),
+            date: "????.??.??".to_owned(),
+            result: GameResult::of(&game),
+            tags: Vec::new(),
+            game,
+        }
+    }
+
+    // A match from agent::play_match. A forfeited game is recorded as
+    // a win for the other side, with the illegal move left out.
+    pub fn from_match(x: &str, o: &str, result: &MatchResult) -> Self {
+        let mut record = Self::new(x, o, result.game.clone());
+        record.result = match result.outcome {
+            MatchOutcome::Won(player) => GameResult::Won(player),
+            MatchOutcome::Draw => GameResult::Draw,
+            MatchOutcome::Forfeit { player, .. } => {
+                GameResult::Won(player.other())
+            }
+        };
+        record
+    }
+}
+
+// PGN strings escape quotes and backslashes with a backslash.
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn unescape(quoted: &str) -> Option<String> {
+    let inner = quoted.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => value.push(chars.next()?),
+            '"' => return None,
+            c => value.push(c),
+        }
+    }
+    Some(value)
+}
+
+// Splits `[Name "value"]` into its name and value.
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, quoted) = inner.split_at(inner.find(char::is_whitespace)?);
+    Some((name.to_owned(), unescape(quoted.trim())?))
+}
+
+impl fmt::Display for GameRecord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let config = self.game.config();
+        let mut tags = vec![
+            ("X", self.x.clone()),
+            ("O", self.o.clone()),
+            ("Date", self.date.clone()),
+            ("Width", config.width.to_string()),
+            ("Height", config.height.to_string()),
+            ("Connect", config.connect.to_string()),
+        ];
+        if self.game.rules() != Rules::Classic {
+            tags.push(("Rules", self.game.rules().to_string()));
+        }
+        tags.push(("Result", self.result.to_string()));
+        for (name, value) in &self.tags {
+            tags.push((name.as_str(), value.clone()));
+        }
+        for (name, value) in tags {
+            writeln!(f, "[{} \"{}\"]", name, escape(&value))?;
+        }
+        writeln!(f)?;
+
+        // The moves, wrapped so that no line is longer than 80 characters.
+        let mut tokens = Vec::new();
+        for (i, &(play, _)) in self.game.history().iter().enumerate() {
+            if i % 2 == 0 {
+                tokens.push(format!("{}.", i / 2 + 1));
+            }
+            tokens.push(play.to_string());
+        }
+        tokens.push(self.result.to_string());
+        let mut line_len = 0;
+        for token in tokens {
+            if line_len > 0 && line_len + 1 + token.len() > 80 {
+                writeln!(f)?;
+                line_len = 0;
+            }
+            if line_len > 0 {
+                write!(f, " ")?;
+                line_len += 1;
+            }
+            write!(f, "{}", token)?;
+            line_len += token.len();
+        }
+        writeln!(f)
+    }
+}
+
+impl FromStr for GameRecord {
+    type Err = RecordError;
+
+    fn from_str(text: &str) -> Result<Self, RecordError> {
+        let mut lines = text.lines().enumerate().peekable();
+        let mut tags = Vec::new();
+        // Tag lines come first; the moves start at the first other line.
+        while let Some(&(i, line)) = lines.peek() {
+            let line = line.trim();
+            if line.is_empty() {
+                lines.next();
+            } else if line.starts_with('[') {
+                let tag = parse_tag(line);
+                tags.push(tag.ok_or(RecordError::BadTag { line: i + 1 })?);
+                lines.next();
+            } else {
+                break;
+            }
+        }
+
+        let mut record = GameRecord::new("?", "?", Game::new());
+        let mut config = GameConfig::default();
+        let mut rules = Rules::Classic;
+        let mut tag_result = None;
+        for (name, value) in tags {
+            let number = || {
+                let size = |&n: &usize| n > 0 && n <= MAX_SIZE;
+                value.parse::<usize>().ok().filter(size).ok_or_else(|| {
+                    RecordError::BadValue {
+                        tag: name.clone(),
+                        value: value.clone(),
+                    }
+                })
+            };
+            match name.as_str() {
+                "X" => record.x = value,
+                "O" => record.o = value,
+                "Date" => record.date = value,
+                "Width" => config.width = number()?,
+                "Height" => config.height = number()?,
+                "Connect" => config.connect = number()?,
+                "Rules" => match Rules::parse(&value) {
+                    Some(parsed) => rules = parsed,
+                    None => {
+                        return Err(RecordError::BadValue { tag: name, value })
+                    }
+                },
+                "Result" => match GameResult::from_token(&value) {
+                    Some(result) => tag_result = Some(result),
+                    None => {
+                        return Err(RecordError::BadValue { tag: name, value })
+                    }
+                },
+                _ => record.tags.push((name, value)),
+            }
+        }
+
+        let mut game = Game::with_rules(config, rules);
+        let mut tokens =
+            lines.flat_map(|(_, line)| line.split_whitespace()).peekable();
+        let mut moves_result = None;
+        // Whether the number before X's next move has been read.
+        let mut numbered = false;
+        for token in tokens.by_ref() {
+            if let Some(result) = GameResult::from_token(token) {
+                moves_result = Some(result);
+                break;
+            }
+            // "1." before X's move; "12." before X's twelfth.
+            let number = game.move_count() + 1;
+            if number % 2 == 1 && !numbered {
+                let expected = number / 2 + 1;
+                if token != format!("{}.", expected) {
+                    let found = token.to_owned();
+                    return Err(RecordError::MoveNumber { expected, found });
+                }
+                numbered = true;
+                continue;
+            }
+            let play = match Move::parse(token) {
+                Some(play) => play,
+                None => return Err(RecordError::BadToken(token.to_owned())),
+            };
+            if let Err(error) = game.try_move(play) {
+                return Err(RecordError::IllegalMove {
+                    number,
+                    col: play.col() + 1,
+                    error,
+                });
+            }
+            numbered = false;
+        }
+        let rest: Vec<&str> = tokens.collect();
+        if !rest.is_empty() {
+            return Err(RecordError::TrailingText(rest.join(" ")));
+        }
+
+        let claimed = match (tag_result, moves_result) {
+            (_, None) => return Err(RecordError::MissingResult),
+            (Some(tag), Some(moves)) if tag != moves => {
+                return Err(RecordError::ResultMismatch { tag, moves })
+            }
+            (_, Some(moves)) => moves,
+        };
+        // A finished game can only have one result; an unfinished one can
+        // still have been decided off the board.
+        let actual = GameResult::of(&game);
+        if actual != GameResult::Unfinished && claimed != actual {
+            return Err(RecordError::WrongResult { claimed, actual });
+        }
+        record.result = claimed;
+        record.game = game;
+        Ok(record)
+    }
+}
+
+#[cfg(test)]
+fn test_record() -> GameRecord {
+    let mut game = Game::with_config(GameConfig::standard());
+    for &col in &[3, 3, 4, 4, 5, 5, 2] {
+        game.make_move(col).unwrap();
+    }
+    let mut record = GameRecord::new("Alice", "search-4", game);
+    record.date = "2026.10.17".to_owned();
+    record
+}
+
+#[test]
+fn test_write_record() {
+    let expected = "\
+[X \"Alice\"]
+[O \"search-4\"]
+[Date \"2026.10.17\"]
+[Width \"7\"]
+[Height \"6\"]
+[Connect \"4\"]
+[Result \"1-0\"]
+
+1. 4 4 2. 5 5 3. 6 6 4. 3 1-0
+";
+    assert_eq!(test_record().to_string(), expected);
+}
+
+#[test]
+fn test_record_rules() {
+    let config = GameConfig::standard();
+    let mut game = Game::with_rules(config, Rules::PopOut);
+    for &play in &[Move::Drop(0), Move::Drop(0), Move::Drop(0), Move::Drop(1)] {
+        game.try_move(play).unwrap();
+    }
+    game.try_move(Move::Pop(0)).unwrap();
+    let record = GameRecord::new("a", "b", game);
+    let text = record.to_string();
+    assert!(text.contains("[Connect \"4\"]\n[Rules \"popout\"]\n"));
+    assert!(text.ends_with("1. 1 1 2. 1 2 3. p1 *\n"));
+    assert_eq!(text.parse(), Ok(record));
+
+    // Without the tag, the same moves break the classic rules.
+    let classic = text.replace("[Rules \"popout\"]\n", "");
+    assert_eq!(
+        classic.parse::<GameRecord>(),
+        Err(RecordError::IllegalMove {
+            number: 5,
+            col: 1,
+            error: BoardError::CantPop(0)
+        })
+    );
+    assert_eq!(
+        "[Rules \"chess\"]\n*".parse::<GameRecord>(),
+        Err(RecordError::BadValue {
+            tag: "Rules".to_owned(),
+            value: "chess".to_owned()
+        })
+    );
+}
+
+#[test]
+fn test_record_round_trip() {
+    let mut record = test_record();
+    record.x = "Bob \"the \\ builder\"".to_owned();
+    record.tags.push(("Event".to_owned(), "Club night".to_owned()));
+    assert_eq!(record.to_string().parse(), Ok(record));
+
+    super::test_random_games(0x7e, |game| {
+        let record = GameRecord::new("a", "b", game.clone());
+        let text = record.to_string();
+        assert!(text.lines().all(|line| line.len() <= 80));
+        assert_eq!(text.parse(), Ok(record));
+    });
+}
+
+#[test]
+fn test_record_forfeit() {
+    use super::agent::play_match;
+    struct Stubborn;
+    impl super::agent::Agent for Stubborn {
+        fn name(&self) -> String {
+            "stubborn".to_owned()
+        }
+        fn choose(&mut self, _game: &Game) -> usize {
+            0
+        }
+    }
+    let config = GameConfig::new(4, 2);
+    let result = play_match(&mut Stubborn, &mut Stubborn, config);
+    let record = GameRecord::from_match("a", "b", &result);
+    assert_eq!(record.result, GameResult::Won(Player::O));
+    assert_eq!(record.to_string().parse(), Ok(record));
+}
+
+#[test]
+fn test_record_errors() {
+    let parse = |moves: &str| {
+        format!("[Width \"7\"]\n[Height \"6\"]\n\n{}", moves)
+            .parse::<GameRecord>()
+    };
+    assert!(parse("1. 4 4 *").is_ok());
+    assert_eq!(
+        "[X Alice]\n1. 4 *".parse::<GameRecord>(),
+        Err(RecordError::BadTag { line: 1 })
+    );
+    assert_eq!(
+        "[Width \"0\"]\n1. 4 *".parse::<GameRecord>(),
+        Err(RecordError::BadValue {
+            tag: "Width".to_owned(),
+            value: "0".to_owned()
+        })
+    );
+    assert_eq!(
+        "[Width \"100000000000\"]\n*".parse::<GameRecord>(),
+        Err(RecordError::BadValue {
+            tag: "Width".to_owned(),
+            value: "100000000000".to_owned()
+        })
+    );
+    assert!("[Height \"255\"]\n*".parse::<GameRecord>().is_ok());
+    assert!("[Connect \"256\"]\n*".parse::<GameRecord>().is_err());
+    assert_eq!(
+        parse("1. 4 4 3. 5 *"),
+        Err(RecordError::MoveNumber { expected: 2, found: "3.".to_owned() })
+    );
+    assert_eq!(parse("1. 4 x *"), Err(RecordError::BadToken("x".to_owned())));
+    assert_eq!(
+        parse("1. 8 *"),
+        Err(RecordError::IllegalMove {
+            number: 1,
+            col: 8,
+            error: BoardError::ColumnOutOfRange(7)
+        })
+    );
+    assert_eq!(parse("1. 4 4"), Err(RecordError::MissingResult));
+    assert_eq!(
+        "[Result \"1-0\"]\n1. 4 4 *".parse::<GameRecord>(),
+        Err(RecordError::ResultMismatch {
+            tag: GameResult::Won(Player::X),
+            moves: GameResult::Unfinished
+        })
+    );
+    assert_eq!(
+        parse("1. 1 2 2. 1 2 3. 1 2 4. 1 0-1"),
+        Err(RecordError::WrongResult {
+            claimed: GameResult::Won(Player::O),
+            actual: GameResult::Won(Player::X)
+        })
+    );
+    assert_eq!(
+        parse("1. 4 * 5"),
+        Err(RecordError::TrailingText("5".to_owned()))
+    );
+}
@@ -0,0 +1,81 @@
+/*
+    A small seedable random number generator (SplitMix64).
+
+    Good enough for shuffling moves and playing random games, and, unlike
+    an RNG seeded from the clock, gives the same numbers every run for the
+    same seed, so tests that use it are reproducible.
+*/
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// Scrambles x into a random-looking number. Never maps two inputs to the
+// same output.
+pub fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(GOLDEN_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+#[derive(Clone, Debug)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let result = splitmix64(self.state);
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        result
+    }
+
+    // Uniform in 0..n. (Very slightly biased for huge n, which we never use.)
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "empty range");
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Uniform in [0, 1).
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // A random element of a slice, or None if it's empty.
+    pub fn choose<T: Copy>(&mut self, items: &[T]) -> Option<T> {
+        if items.is_empty() {
+            None
+        } else {
+            Some(items[self.below(items.len())])
+        }
+    }
+}
+
+#[test]
+fn test_rng_reproducible() {
+    let mut a = Rng::new(42);
+    let mut b = Rng::new(42);
+    let mut c = Rng::new(43);
+    let xs: Vec<u64> = (0..10).map(|_| a.next_u64()).collect();
+    let ys: Vec<u64> = (0..10).map(|_| b.next_u64()).collect();
+    let zs: Vec<u64> = (0..10).map(|_| c.next_u64()).collect();
+    assert_eq!(xs, ys);
+    assert_ne!(xs, zs);
+}
+
+#[test]
+fn test_rng_ranges() {
+    let mut rng = Rng::new(7);
+    let mut counts = [0; 5];
+    for _ in 0..5000 {
+        counts[rng.below(5)] += 1;
+        let x = rng.next_f64();
+        assert!((0.0..1.0).contains(&x));
+    }
+    assert!(counts.iter().all(|&count| count > 800 && count < 1200));
+    assert_eq!(rng.choose::<usize>(&[]), None);
+    assert_eq!(rng.choose(&[3]), Some(3));
+}
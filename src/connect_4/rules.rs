@@ -0,0 +1,674 @@
+/*
+    Rule variants: PopOut and Pop 10.
+
+    Under the classic rules every move drops a piece into a column. Both
+    variants add a second kind of move: popping one of your own pieces out
+    of the bottom of a column, so that everything above it falls a row.
+
+    PopOut: on your turn, either drop a piece or pop one of yours. A pop
+    moves a whole column at once, so it can complete lines for either
+    player, or for both: if only one player has a line they win, and if
+    both do, the player who popped wins. A full board isn't a draw, since
+    there may still be pops to make. Instead the game is drawn when the
+    same position comes up for the third time, or when the player to move
+    has no move at all.
+
+    Pop 10: first the board is filled, the bottom row before any piece
+    goes in the row above it, and so on up. After that every turn starts
+    with a pop. If the popped piece was part of a line of config.connect,
+    it's set aside and the same player moves again. Otherwise they must
+    drop it back in, in a different column if any has room, and the turn
+    passes. The first player to set aside 10 pieces wins: lines on the
+    board win nothing by themselves. A player with no piece of their own
+    on the bottom row can't move, so their turn is skipped; if neither
+    player can move, the game is drawn.
+
+    Under Pop 10 the pop and the drop that follows are separate moves, each
+    in the history, and the turn doesn't pass in between.
+
+    Only Game knows about the variants: the engines, bitboards, books,
+    perft and the analysis functions all play the classic rules. Rather
+    than quietly search a variant as if it were classic, they turn it down
+    with a ClassicOnly error.
+*/
+
+use std::error;
+use std::fmt;
+
+use super::{BoardError, Game, Player};
+
+// How many pieces to set aside to win at Pop 10.
+pub const POP_10_TARGET: usize = 10;
+
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub enum Rules {
+    #[default]
+    Classic,
+    PopOut,
+    Pop10,
+}
+
+impl Rules {
+    // The names Display uses: "classic", "popout" or "pop10".
+    pub fn parse(name: &str) -> Option<Rules> {
+        match name {
+            "classic" => Some(Rules::Classic),
+            "popout" => Some(Rules::PopOut),
+            "pop10" => Some(Rules::Pop10),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Rules {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rules::Classic => write!(f, "classic"),
+            Rules::PopOut => write!(f, "popout"),
+            Rules::Pop10 => write!(f, "pop10"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Move {
+    // A new piece on top of the column.
+    Drop(usize),
+    // The bottom piece out of the column.
+    Pop(usize),
+}
+
+impl Move {
+    pub fn col(self) -> usize {
+        match self {
+            Move::Drop(col) | Move::Pop(col) => col,
+        }
+    }
+
+    // "4" drops into column 4 and "p4" pops from it, counting from 1 as
+    // on screen.
+    pub fn parse(text: &str) -> Option<Move> {
+        let (pop, number) = match text.strip_prefix('p') {
+            Some(number) => (true, number),
+            None => (false, text),
+        };
+        let col = number.parse::<usize>().ok().filter(|&col| col > 0)? - 1;
+        Some(if pop { Move::Pop(col) } else { Move::Drop(col) })
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Move::Drop(col) => write!(f, "{}", col + 1),
+            Move::Pop(col) => write!(f, "p{}", col + 1),
+        }
+    }
+}
+
+// Returned by code that only knows the classic rules when it's handed a
+// game played by others.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ClassicOnly(pub Rules);
+
+impl fmt::Display for ClassicOnly {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "only the classic rules are supported, not {}", self.0)
+    }
+}
+
+impl error::Error for ClassicOnly {}
+
+// The pub(super) functions are for Game's own play, undo and status, in
+// connect_4.rs.
+impl Game {
+    // Pieces the player has set aside; always 0 except under Pop 10.
+    pub fn captured(&self, player: Player) -> usize {
+        self.set_aside.iter().filter(|&&i| self.history[i].1 == player).count()
+    }
+
+    // For the engines and the rest, which only play the classic rules.
+    pub fn require_classic(&self) -> Result<(), ClassicOnly> {
+        match self.rules {
+            Rules::Classic => Ok(()),
+            rules => Err(ClassicOnly(rules)),
+        }
+    }
+
+    // Every move the player to move can make: drops in column order, then
+    // pops.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        if self.is_over() {
+            return Vec::new();
+        }
+        self.available_moves()
+    }
+
+    // Makes a move for whoever's turn it is, if the rules allow it.
+    pub fn try_move(&mut self, play: Move) -> Result<(), BoardError> {
+        let col = play.col();
+        if col >= self.width() {
+            return Err(BoardError::ColumnOutOfRange(col));
+        }
+        if self.is_over() {
+            return Err(BoardError::GameOver);
+        }
+        self.check(play)?;
+        let player = self.to_play;
+        match play {
+            Move::Drop(col) => self.place(col, player),
+            Move::Pop(col) => self.pop(col, player),
+        }
+        self.redo.clear();
+        Ok(())
+    }
+
+    // The moves allowed if the game weren't over.
+    fn available_moves(&self) -> Vec<Move> {
+        let cols = 0..self.width();
+        cols.clone()
+            .map(Move::Drop)
+            .chain(cols.map(Move::Pop))
+            .filter(|&play| self.check(play).is_ok())
+            .collect()
+    }
+
+    // Whether the rules allow the move, for a column on the board, not
+    // counting whether the game is over.
+    fn check(&self, play: Move) -> Result<(), BoardError> {
+        match play {
+            Move::Drop(col) => {
+                if self.board[col].len() == self.height() {
+                    return Err(BoardError::ColumnFull(col));
+                }
+                if self.rules == Rules::Pop10 && !self.pop_10_drop(col) {
+                    return Err(BoardError::CantDrop(col));
+                }
+            }
+            Move::Pop(col) => {
+                let allowed = match self.rules {
+                    Rules::Classic => false,
+                    Rules::PopOut => true,
+                    Rules::Pop10 => {
+                        !self.filling() && self.pending_drop().is_none()
+                    }
+                };
+                if !allowed || self.board[col].first() != Some(&self.to_play) {
+                    return Err(BoardError::CantPop(col));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Under Pop 10, whether a drop into col (which has room) is allowed.
+    fn pop_10_drop(&self, col: usize) -> bool {
+        if self.filling() {
+            let lowest = self.board.iter().map(|column| column.len()).min();
+            return Some(self.board[col].len()) == lowest;
+        }
+        match self.pending_drop() {
+            // The popped column is the last resort.
+            Some(from) => {
+                col != from
+                    || (0..self.width()).all(|other| {
+                        other == from
+                            || self.board[other].len() == self.height()
+                    })
+            }
+            None => false,
+        }
+    }
+
+    // Under Pop 10, whether the board is still being filled. Once play
+    // starts it is only ever short of pieces that are set aside, or one
+    // waiting to be dropped back in.
+    fn filling(&self) -> bool {
+        self.set_aside.is_empty()
+            && self.pending_drop().is_none()
+            && !self.is_full()
+    }
+
+    // Under Pop 10, the column a piece was just popped from, if it has to
+    // be dropped back in.
+    pub(super) fn pending_drop(&self) -> Option<usize> {
+        let last = self.history.len().checked_sub(1)?;
+        match self.history[last] {
+            (Move::Pop(col), _)
+                if self.rules == Rules::Pop10
+                    && self.set_aside.last() != Some(&last) =>
+            {
+                Some(col)
+            }
+            _ => None,
+        }
+    }
+
+    // After each play, caches whether a PopOut or Pop 10 game is now over
+    // with no winner, so that is_over and status don't work it out again
+    // every time they're asked.
+    pub(super) fn update_drawn(&mut self) {
+        self.drawn = self.rules != Rules::Classic
+            && self.won.is_none()
+            && self.is_drawn();
+    }
+
+    // Under PopOut or Pop 10, with nobody having won: whether the game is
+    // over anyway.
+    fn is_drawn(&self) -> bool {
+        (self.rules == Rules::PopOut && self.repetitions() >= 3)
+            || self.available_moves().is_empty()
+    }
+
+    // How many times the current position has come up, this time included.
+    // Positions are compared by hash, which is nearly always the same
+    // thing.
+    fn repetitions(&self) -> usize {
+        1 + self.hashes.iter().filter(|&&hash| hash == self.hash).count()
+    }
+
+    // The unchecked part of a pop, like place for a drop.
+    pub(super) fn pop(&mut self, col: usize, player: Player) {
+        // Whether the piece is in a line has to be checked before it goes.
+        let in_line = self.win_through((col, 0)).is_some();
+        self.hashes.push(self.hash);
+        self.turns.push(self.to_play);
+        let mut column = self.board[col].clone();
+        column.remove(0);
+        self.restack(col, column);
+        self.filled -= 1;
+        self.history.push((Move::Pop(col), player));
+        if self.rules == Rules::Pop10 {
+            // The same player moves next either way: again, or to drop
+            // the piece back in.
+            if in_line {
+                self.set_aside.push(self.history.len() - 1);
+                if self.captured(player) == POP_10_TARGET {
+                    self.won = Some((player, Vec::new()));
+                }
+                self.skip_if_stuck();
+            }
+        } else {
+            self.set_to_play(player.other());
+            self.won = self.pop_winner(player);
+        }
+        self.update_drawn();
+    }
+
+    // Under Pop 10, passes the turn if the player to move can't move but
+    // the other player can.
+    pub(super) fn skip_if_stuck(&mut self) {
+        if self.won.is_none() && self.available_moves().is_empty() {
+            let player = self.to_play;
+            self.set_to_play(player.other());
+            if self.available_moves().is_empty() {
+                self.set_to_play(player);
+            }
+        }
+    }
+
+    // Takes back the pop at the end of the history; unplace puts the turn
+    // back.
+    pub(super) fn unpop(&mut self, col: usize, player: Player) {
+        self.history.pop();
+        self.hashes.pop();
+        if self.set_aside.last() == Some(&self.history.len()) {
+            self.set_aside.pop();
+        }
+        let mut column = self.board[col].clone();
+        column.insert(0, player);
+        self.restack(col, column);
+        self.filled += 1;
+    }
+
+    // Replaces a whole column, keeping the hashes up to date: every piece
+    // in it may have moved.
+    fn restack(&mut self, col: usize, column: Vec<Player>) {
+        let old = std::mem::replace(&mut self.board[col], column);
+        for (row, &player) in old.iter().enumerate() {
+            self.toggle_hash(col, row, player);
+        }
+        for row in 0..self.board[col].len() {
+            let player = self.board[col][row];
+            self.toggle_hash(col, row, player);
+        }
+    }
+
+    // After a pop any piece in the column may have joined a line, so we
+    // look at the whole board. The player who popped wins a tie.
+    fn pop_winner(
+        &self,
+        popper: Player,
+    ) -> Option<(Player, Vec<(usize, usize)>)> {
+        let line = |player| {
+            self.blocks().find(|block| {
+                block.iter().all(|&(i, j)| self.get(i, j) == Some(player))
+            })
+        };
+        line(popper).map(|cells| (popper, cells)).or_else(|| {
+            line(popper.other()).map(|cells| (popper.other(), cells))
+        })
+    }
+}
+
+// Builds a game from columns written bottom first, e.g. "XOX".
+#[cfg(test)]
+fn test_game(
+    rules: Rules,
+    height: usize,
+    columns: &[&str],
+    to_play: Player,
+) -> Game {
+    let board = columns
+        .iter()
+        .map(|column| {
+            column
+                .chars()
+                .map(|c| if c == 'X' { Player::X } else { Player::O })
+                .collect()
+        })
+        .collect();
+    let mut game = Game::from_columns(
+        super::GameConfig::new(columns.len(), height),
+        board,
+        to_play,
+    );
+    game.rules = rules;
+    // from_columns looks for lines, which don't win under Pop 10.
+    if rules == Rules::Pop10 {
+        game.won = None;
+    }
+    game.update_drawn();
+    game
+}
+
+#[test]
+fn test_move_notation() {
+    assert_eq!(Move::parse("4"), Some(Move::Drop(3)));
+    assert_eq!(Move::parse("p10"), Some(Move::Pop(9)));
+    assert_eq!(Move::parse("p0"), None);
+    assert_eq!(Move::parse("x4"), None);
+    assert_eq!(Move::Pop(2).to_string(), "p3");
+    assert_eq!(Move::Drop(2).to_string(), "3");
+    for &rules in &[Rules::Classic, Rules::PopOut, Rules::Pop10] {
+        assert_eq!(Rules::parse(&rules.to_string()), Some(rules));
+    }
+}
+
+#[test]
+fn test_popout_pop() {
+    let config = super::GameConfig::standard();
+    let mut game = Game::with_rules(config, Rules::PopOut);
+    for &col in &[0, 0, 0] {
+        game.make_move(col).unwrap();
+    }
+    // Only your own pieces can be popped.
+    assert_eq!(game.try_move(Move::Pop(0)), Err(BoardError::CantPop(0)));
+    assert_eq!(game.try_move(Move::Pop(1)), Err(BoardError::CantPop(1)));
+    game.make_move(1).unwrap();
+    let before = game.clone();
+    game.try_move(Move::Pop(0)).unwrap();
+    assert_eq!(game.get(0, 0), Some(Player::O));
+    assert_eq!(game.get(0, 1), Some(Player::X));
+    assert_eq!(game.get(0, 2), None);
+    assert_eq!(game.to_play(), Player::O);
+    let board = game.board.clone();
+    let rebuilt = Game::from_columns(config, board, Player::O);
+    assert_eq!(game.zobrist(), rebuilt.zobrist());
+    let after = game.clone();
+
+    assert_eq!(game.undo(), Some((Move::Pop(0), Player::X)));
+    assert_eq!(game, before);
+    assert_eq!(game.zobrist(), before.zobrist());
+    assert_eq!(game.redo(), Some((Move::Pop(0), Player::X)));
+    assert_eq!(game, after);
+
+    // The classic rules have no pops.
+    let mut game = Game::with_config(config);
+    game.make_move(0).unwrap();
+    game.make_move(1).unwrap();
+    assert_eq!(game.try_move(Move::Pop(0)), Err(BoardError::CantPop(0)));
+    assert!(game.legal_moves().iter().all(|play| match play {
+        Move::Drop(_) => true,
+        Move::Pop(_) => false,
+    }));
+}
+
+#[test]
+fn test_popout_pop_wins() {
+    // Popping column 1 drops O's piece into a line along the bottom and
+    // X's into one along the row above: the player who popped wins.
+    let columns = ["XOX", "OX", "OX", "OX", "", "", "O"];
+    let mut game = test_game(Rules::PopOut, 6, &columns, Player::X);
+    assert_eq!(game.winner(), None);
+    game.try_move(Move::Pop(0)).unwrap();
+    assert_eq!(game.winner(), Some(Player::X));
+    match game.status() {
+        super::GameStatus::Won { player, mut cells } => {
+            assert_eq!(player, Player::X);
+            cells.sort();
+            assert_eq!(cells, vec![(0, 1), (1, 1), (2, 1), (3, 1)]);
+        }
+        status => panic!("expected a win, got {:?}", status),
+    }
+    assert!(game.legal_moves().is_empty());
+
+    // Here the pop only makes a line for O, so O wins.
+    let columns = ["XO", "OX", "OX", "OX", "", "", ""];
+    let mut game = test_game(Rules::PopOut, 6, &columns, Player::X);
+    game.try_move(Move::Pop(0)).unwrap();
+    assert_eq!(game.winner(), Some(Player::O));
+    game.undo();
+    assert_eq!(game.winner(), None);
+}
+
+#[test]
+fn test_popout_draws() {
+    // A full board isn't the end: O can still pop.
+    let config = super::GameConfig::new(4, 4);
+    let columns = ["XOXO", "XOXO", "OXOX", "OXOX"];
+    let game = test_game(Rules::PopOut, 4, &columns, Player::O);
+    assert!(!game.is_over());
+    assert_eq!(game.legal_moves(), vec![Move::Pop(2), Move::Pop(3)]);
+
+    // Dropping and popping back to the start, twice over, repeats the
+    // empty board for the third time.
+    let mut game = Game::with_rules(config, Rules::PopOut);
+    let cycle = [Move::Drop(0), Move::Drop(1), Move::Pop(0), Move::Pop(1)];
+    for &play in cycle.iter().chain(&cycle[..3]) {
+        game.try_move(play).unwrap();
+        assert!(!game.is_over());
+    }
+    game.try_move(Move::Pop(1)).unwrap();
+    assert_eq!(game.status(), super::GameStatus::Draw);
+    assert_eq!(game.try_move(Move::Drop(0)), Err(BoardError::GameOver));
+    game.undo();
+    assert!(!game.is_over());
+}
+
+#[test]
+fn test_pop_10() {
+    let config = super::GameConfig::new(4, 4);
+    let mut game = Game::with_rules(config, Rules::Pop10);
+    game.make_move(0).unwrap();
+    // The bottom row fills up first, whichever way the piece is played.
+    assert_eq!(game.make_move(0), Err(BoardError::CantDrop(0)));
+    assert_eq!(game.try_play(0, Player::O), Err(BoardError::CantDrop(0)));
+    // And only by the player to move.
+    let not_x = Err(BoardError::NotYourTurn(Player::X));
+    assert_eq!(game.try_play(1, Player::X), not_x);
+    assert_eq!(game.try_move(Move::Pop(0)), Err(BoardError::CantPop(0)));
+    // The same pattern as test_status_draw: no lines anywhere.
+    for &col in &[2, 1, 3, 2, 0, 3, 1, 0, 2, 1, 3, 2, 0, 3, 1] {
+        game.make_move(col).unwrap();
+    }
+    assert_eq!(game.to_play(), Player::X);
+    assert_eq!(game.legal_moves(), vec![Move::Pop(0), Move::Pop(1)]);
+
+    // A piece that isn't in a line goes back in, and only then does the
+    // turn pass. Column 0 is the only one with room.
+    game.try_move(Move::Pop(0)).unwrap();
+    assert_eq!(game.to_play(), Player::X);
+    assert_eq!(game.captured(Player::X), 0);
+    assert_eq!(game.legal_moves(), vec![Move::Drop(0)]);
+    assert_eq!(game.try_move(Move::Drop(1)), Err(BoardError::ColumnFull(1)));
+    game.try_move(Move::Drop(0)).unwrap();
+    assert_eq!(game.to_play(), Player::O);
+    // It went in on top, so O's piece is at the bottom now.
+    let pops = vec![Move::Pop(0), Move::Pop(2), Move::Pop(3)];
+    assert_eq!(game.legal_moves(), pops);
+    assert!(!game.is_over());
+}
+
+#[test]
+fn test_pop_10_set_aside() {
+    // With one in a row, every piece is in a line, so every pop sets a
+    // piece aside and X moves again. X filled the left half.
+    let config =
+        super::GameConfig { connect: 1, ..super::GameConfig::new(6, 4) };
+    let mut game = Game::with_rules(config, Rules::Pop10);
+    for _ in 0..4 {
+        for &col in &[0, 3, 1, 4, 2, 5] {
+            game.make_move(col).unwrap();
+        }
+    }
+    assert_eq!(game.winner(), None);
+    for &col in &[0, 0, 0, 0, 1, 1, 1, 1, 2] {
+        game.try_move(Move::Pop(col)).unwrap();
+        assert_eq!(game.to_play(), Player::X);
+    }
+    assert_eq!(game.captured(Player::X), 9);
+    assert_eq!(game.captured(Player::O), 0);
+    // Set-aside pieces don't come back, so there's nothing to drop.
+    assert_eq!(game.make_move(0), Err(BoardError::CantDrop(0)));
+    assert_eq!(game.try_move(Move::Pop(3)), Err(BoardError::CantPop(3)));
+    game.try_move(Move::Pop(2)).unwrap();
+    assert_eq!(game.captured(Player::X), POP_10_TARGET);
+    assert_eq!(
+        game.status(),
+        super::GameStatus::Won { player: Player::X, cells: Vec::new() }
+    );
+    game.undo();
+    assert_eq!(game.captured(Player::X), 9);
+    assert!(!game.is_over());
+}
+
+#[test]
+fn test_pop_10_drop_elsewhere() {
+    // X's line along the bottom is the only line on the board.
+    let columns = ["XOX", "XXO", "XOX", "XXO"];
+    let mut game = test_game(Rules::Pop10, 3, &columns, Player::X);
+    assert_eq!(game.legal_moves().len(), 4);
+    game.try_move(Move::Pop(0)).unwrap();
+    assert_eq!(game.captured(Player::X), 1);
+    assert_eq!(
+        game.legal_moves(),
+        vec![Move::Pop(1), Move::Pop(2), Move::Pop(3)]
+    );
+    // With O now in column 0 the line is broken, so this piece has to go
+    // back in, and not where it came from while column 0 has room.
+    game.try_move(Move::Pop(1)).unwrap();
+    assert_eq!(game.captured(Player::X), 1);
+    assert_eq!(game.legal_moves(), vec![Move::Drop(0)]);
+    assert_eq!(game.try_move(Move::Drop(1)), Err(BoardError::CantDrop(1)));
+    game.try_move(Move::Drop(0)).unwrap();
+    assert_eq!(game.to_play(), Player::O);
+
+    // Undoing both halves of the turn, and the pop before, gets back to
+    // the start.
+    let start = test_game(Rules::Pop10, 3, &columns, Player::X);
+    for _ in 0..3 {
+        game.undo();
+    }
+    assert_eq!(game, start);
+    assert_eq!(game.captured(Player::X), 0);
+}
+
+#[test]
+fn test_random_variant_games() {
+    // Random play under each set of rules, capped since PopOut and Pop 10
+    // games can go on for a long time. legal_moves must list exactly the
+    // moves try_move accepts, and undo must retrace every position.
+    let mut state: u64 = 0x25;
+    for &rules in &[Rules::Classic, Rules::PopOut, Rules::Pop10] {
+        let mut wins = 0;
+        for &connect in &[3, 4] {
+            let config =
+                super::GameConfig { connect, ..super::GameConfig::standard() };
+            for _ in 0..10 {
+                let mut game = Game::with_rules(config, rules);
+                let mut seen = vec![game.clone()];
+                while !game.is_over() && game.move_count() < 300 {
+                    let legal = game.legal_moves();
+                    for col in 0..config.width {
+                        for &play in &[Move::Drop(col), Move::Pop(col)] {
+                            let ok = game.clone().try_move(play).is_ok();
+                            assert_eq!(ok, legal.contains(&play), "{:?}", play);
+                        }
+                    }
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    let play = legal[state as usize % legal.len()];
+                    game.try_move(play).unwrap();
+                    seen.push(game.clone());
+                    let drawn = game.won.is_none() && game.is_drawn();
+                    assert_eq!(game.drawn, drawn && rules != Rules::Classic);
+                }
+                if let Some(player) = game.winner() {
+                    if rules == Rules::Pop10 {
+                        assert_eq!(game.captured(player), POP_10_TARGET);
+                    }
+                    wins += 1;
+                }
+                while game.undo().is_some() {
+                    seen.pop();
+                    let expected = seen.last().unwrap();
+                    assert_eq!(&game, expected);
+                    assert_eq!(game.zobrist(), expected.zobrist());
+                    assert!(!game.is_over());
+                }
+            }
+        }
+        // Check that the games got somewhere.
+        assert!(wins > 0, "no wins under {}", rules);
+    }
+}
+
+#[test]
+fn test_classic_only() {
+    use super::agent::{Agent, FirstLegalAgent, SearchAgent};
+    use super::book::Book;
+    use super::engine::Engine;
+    use super::mcts::{Budget, Mcts};
+
+    // Pop 10 after one drop, where only the bottom row takes a piece; and
+    // a full PopOut board with pops still to make.
+    let mut pop_10 =
+        Game::with_rules(super::GameConfig::new(4, 4), Rules::Pop10);
+    pop_10.make_move(0).unwrap();
+    let columns = ["XOXO", "XOXO", "OXOX", "OXOX"];
+    let popout = test_game(Rules::PopOut, 4, &columns, Player::X);
+    assert!(!popout.is_over());
+    let book = Book::generate(super::GameConfig::new(4, 4), 1, 2).unwrap();
+    for game in &[pop_10, popout] {
+        let error = Some(ClassicOnly(game.rules()));
+        assert_eq!(Engine::new(4).search(game).err(), error);
+        let limit = std::time::Duration::from_secs(1);
+        assert_eq!(Engine::new(0).search_for(game, limit).err(), error);
+        assert_eq!(Engine::new(4).search_parallel(game, 2).err(), error);
+        assert_eq!(
+            Mcts::new(Budget::Iterations(10), 1).search(game).err(),
+            error
+        );
+        // The agents forfeit with a column off the board.
+        assert_eq!(SearchAgent::new(2).choose(game), game.width());
+        assert_eq!(FirstLegalAgent.choose(game), game.width());
+        assert_eq!(book.lookup(game).err(), error);
+        assert_eq!(book.search(game, &mut Engine::new(2)).err(), error);
+        assert_eq!(game.perft(2).err(), error);
+        assert_eq!(game.hint().err(), error);
+        assert_eq!(game.threats().err(), error);
+    }
+    assert_eq!(Game::new().require_classic(), Ok(()));
+}
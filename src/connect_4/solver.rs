@@ -0,0 +1,345 @@
+/*
+    Solving small boards completely.
+
+    To strongly solve a game is to know the result of perfect play from
+    every position that can come up, not just from the start. On small
+    boards there are few enough positions to visit them all: we search the
+    whole game tree once, remembering the value of each position so that
+    it's only worked out once however many ways it can be reached.
+
+    This uses bitboards (bitboard.rs) rather than Game, since the table
+    holds one entry per position and BitBoard::key gives each one a
+    compact, unique number. Mirror images share an entry.
+
+    Counting mirror images once, 4x4 has about 80 thousand positions
+    (a draw), 5x4 about 2 million (a draw) and 6x4 about 47 million (a
+    win for O); a release build solves them in well under a second, a few
+    seconds and about a minute. 6x5 and anything larger have too many
+    positions to hold in memory this way, so they aren't supported:
+    solve() turns down any board of more than MAX_CELLS cells straight
+    away rather than running until memory runs out.
+
+    The table file is binary, every number little-endian:
+        "C4SV" version width height connect count(u64)
+        then per position: key (just enough bytes for the board)
+        and value (i8: 0 draw, k win in k plies, -1 - k loss in k plies)
+*/
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use super::bitboard::BitBoard;
+use super::engine::Outcome;
+use super::{Game, GameConfig};
+
+// The largest board solve() takes on: 6x4, or anything else with as few
+// cells.
+pub const MAX_CELLS: usize = 24;
+
+// Returned by solve() for a board with more than MAX_CELLS cells.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TooBig(pub GameConfig);
+
+impl fmt::Display for TooBig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "a {}x{} board is too big to solve (at most {} cells)",
+            self.0.width, self.0.height, MAX_CELLS
+        )
+    }
+}
+
+impl error::Error for TooBig {}
+
+const MAGIC: &[u8; 4] = b"C4SV";
+const VERSION: u8 = 1;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Solution {
+    pub config: GameConfig,
+    // From the point of view of the player to move, counted in plies;
+    // never Outcome::Unknown.
+    values: HashMap<u128, Outcome>,
+}
+
+impl Solution {
+    pub fn solve(config: GameConfig) -> Result<Self, TooBig> {
+        if config.width * config.height > MAX_CELLS {
+            return Err(TooBig(config));
+        }
+        // Anything up to MAX_CELLS fits in a bitboard.
+        let board = BitBoard::new(config).map_err(|_| TooBig(config))?;
+        let mut solution = Solution { config, values: HashMap::new() };
+        solution.value_of(board);
+        Ok(solution)
+    }
+
+    // Negamax over the whole tree, with no depth limit and no pruning:
+    // every child's exact value is needed anyway, to go in the table.
+    fn value_of(&mut self, board: BitBoard) -> Outcome {
+        let key = canonical_key(&board);
+        if let Some(&value) = self.values.get(&key) {
+            return value;
+        }
+        let value = if board.winner().is_some() {
+            // Only the player who just moved can have won.
+            Outcome::Loss(0)
+        } else if board.is_over() {
+            Outcome::Draw
+        } else {
+            let plays: Vec<usize> = board.valid_plays().collect();
+            let mut best = Outcome::Loss(0);
+            for col in plays {
+                let mut child = board;
+                child.make_move(col).unwrap();
+                let value = match self.value_of(child) {
+                    Outcome::Win(plies) => Outcome::Loss(plies + 1),
+                    Outcome::Loss(plies) => Outcome::Win(plies + 1),
+                    value => value,
+                };
+                if rank(value) > rank(best) {
+                    best = value;
+                }
+            }
+            best
+        };
+        self.values.insert(key, value);
+        value
+    }
+
+    // The number of positions, counting mirror images once.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    // None if the game is on a different board or isn't classic, or its
+    // position can't be reached in play.
+    pub fn value(&self, game: &Game) -> Option<Outcome> {
+        if game.config() != self.config {
+            return None;
+        }
+        let board = BitBoard::try_from(game).ok()?;
+        self.values.get(&canonical_key(&board)).cloned()
+    }
+
+    // Every move that keeps the best result available.
+    pub fn best_moves(&self, game: &Game) -> Vec<usize> {
+        let value = match self.value(game) {
+            Some(value) if !game.is_over() => value,
+            _ => return Vec::new(),
+        };
+        game.valid_plays()
+            .filter(|&col| {
+                let mut after = game.clone();
+                after.make_move(col).unwrap();
+                let reply = self.value(&after);
+                match value {
+                    Outcome::Win(plies) => {
+                        reply == Some(Outcome::Loss(plies - 1))
+                    }
+                    Outcome::Loss(plies) => {
+                        reply == Some(Outcome::Win(plies - 1))
+                    }
+                    _ => reply == Some(value),
+                }
+            })
+            .collect()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        self.write_to(&mut file)?;
+        file.flush()
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(&mut BufReader::new(File::open(path)?))
+    }
+
+    pub fn write_to<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        let config = self.config;
+        out.write_all(MAGIC)?;
+        for &n in &[config.width, config.height, config.connect] {
+            if n > usize::from(u8::MAX) {
+                return Err(invalid(io::ErrorKind::InvalidInput, "too big"));
+            }
+        }
+        out.write_all(&[
+            VERSION,
+            config.width as u8,
+            config.height as u8,
+            config.connect as u8,
+        ])?;
+        out.write_all(&(self.values.len() as u64).to_le_bytes())?;
+        let key_len = key_bytes(config);
+        // Sorted, so the same solution always makes the same file.
+        let mut keys: Vec<&u128> = self.values.keys().collect();
+        keys.sort_unstable();
+        for key in keys {
+            let value = match self.values[key] {
+                Outcome::Draw => 0,
+                Outcome::Win(plies) => plies as i8,
+                // Loss(0), a game that's already lost, isn't a draw.
+                Outcome::Loss(plies) => -1 - plies as i8,
+                Outcome::Unknown => unreachable!("solved positions are known"),
+            };
+            out.write_all(&key.to_le_bytes()[..key_len])?;
+            out.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(input: &mut R) -> io::Result<Self> {
+        let mut header = [0; 16];
+        input.read_exact(&mut header)?;
+        if &header[..4] != MAGIC || header[4] != VERSION {
+            return Err(invalid(io::ErrorKind::InvalidData, "not a solution"));
+        }
+        let config = GameConfig {
+            width: usize::from(header[5]),
+            height: usize::from(header[6]),
+            connect: usize::from(header[7]),
+        };
+        if config.width == 0 || config.height == 0 || config.connect == 0 {
+            return Err(invalid(io::ErrorKind::InvalidData, "empty board"));
+        }
+        if !BitBoard::fits(config) {
+            return Err(invalid(io::ErrorKind::InvalidData, "board too big"));
+        }
+        let mut count = [0; 8];
+        count.copy_from_slice(&header[8..]);
+        let count = u64::from_le_bytes(count);
+
+        let key_len = key_bytes(config);
+        let mut values = HashMap::new();
+        for _ in 0..count {
+            let mut key = [0; 16];
+            input.read_exact(&mut key[..key_len])?;
+            let mut value = [0; 1];
+            input.read_exact(&mut value)?;
+            let value = match value[0] as i8 {
+                0 => Outcome::Draw,
+                plies if plies > 0 => Outcome::Win(plies as usize),
+                plies => Outcome::Loss((-1 - plies) as usize),
+            };
+            values.insert(u128::from_le_bytes(key), value);
+        }
+        Ok(Solution { config, values })
+    }
+}
+
+fn canonical_key(board: &BitBoard) -> u128 {
+    board.key().min(board.mirrored().key())
+}
+
+// Better results rank higher: winning sooner, then drawing, then losing
+// later.
+fn rank(value: Outcome) -> i32 {
+    match value {
+        Outcome::Win(plies) => 1000 - plies as i32,
+        Outcome::Draw | Outcome::Unknown => 0,
+        Outcome::Loss(plies) => -1000 + plies as i32,
+    }
+}
+
+// Keys use a column of height + 1 bits per column of the board.
+fn key_bytes(config: GameConfig) -> usize {
+    (config.width * (config.height + 1)).div_ceil(8)
+}
+
+fn invalid(kind: io::ErrorKind, message: &str) -> io::Error {
+    io::Error::new(kind, message)
+}
+
+#[cfg(test)]
+fn small_configs() -> Vec<GameConfig> {
+    vec![
+        GameConfig::new(4, 4),
+        GameConfig { connect: 3, ..GameConfig::new(4, 3) },
+        GameConfig { connect: 3, ..GameConfig::new(3, 3) },
+    ]
+}
+
+#[test]
+fn test_solve_small_boards() {
+    let solution = Solution::solve(GameConfig::new(4, 4)).unwrap();
+    let game = Game::with_config(solution.config);
+    // Four in a row on a 4x4 board: neither side can force it.
+    assert_eq!(solution.value(&game), Some(Outcome::Draw));
+    assert_eq!(solution.best_moves(&game).len(), 4);
+    // Three in a row on a 4x3 board: the first player wins.
+    let config = GameConfig { connect: 3, ..GameConfig::new(4, 3) };
+    let solution = Solution::solve(config).unwrap();
+    let value = solution.value(&Game::with_config(config)).unwrap();
+    assert!(matches!(value, Outcome::Win(_)), "{:?}", value);
+    assert_eq!(solution.value(&Game::new()), None);
+    assert!(Solution::solve(GameConfig::new(16, 8)).is_err());
+    // Too many positions, though they'd fit in a bitboard: turned down
+    // at once rather than searched.
+    for &(width, height) in &[(6, 5), (9, 9), (25, 1)] {
+        let config = GameConfig::new(width, height);
+        assert_eq!(Solution::solve(config), Err(TooBig(config)));
+    }
+}
+
+#[test]
+fn test_solution_matches_engine() {
+    for config in small_configs() {
+        let solution = Solution::solve(config).unwrap();
+        let mut checked = 0;
+        super::test_random_games(0x23, |game| {
+            if game.config() != config || checked >= 40 {
+                return;
+            }
+            checked += 1;
+            let empty = config.width * config.height - game.move_count();
+            let result =
+                super::engine::Engine::new(empty).search(game).unwrap();
+            assert_eq!(solution.value(game), Some(result.outcome));
+            if let Some(best) = result.best {
+                assert!(solution.best_moves(game).contains(&best));
+            }
+        });
+        // test_random_games only plays some of the boards.
+        let mut game = Game::with_config(config);
+        while !game.is_over() {
+            let empty = config.width * config.height - game.move_count();
+            let result =
+                super::engine::Engine::new(empty).search(&game).unwrap();
+            assert_eq!(solution.value(&game), Some(result.outcome));
+            game.make_move(solution.best_moves(&game)[0]).unwrap();
+        }
+    }
+}
+
+#[test]
+fn test_solution_file() {
+    let config = GameConfig { connect: 3, ..GameConfig::new(3, 3) };
+    let solution = Solution::solve(config).unwrap();
+    let mut bytes = Vec::new();
+    solution.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes.len(), 16 + (2 + 1) * solution.len());
+    assert_eq!(Solution::read_from(&mut &bytes[..]).unwrap(), solution);
+
+    let path = std::env::temp_dir().join("connect_4_solution_test.bin");
+    solution.save(&path).unwrap();
+    assert_eq!(Solution::load(&path).unwrap(), solution);
+    std::fs::remove_file(&path).unwrap();
+
+    let mut wrong = bytes.clone();
+    wrong[3] = b'K';
+    let error = Solution::read_from(&mut &wrong[..]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    let error = Solution::read_from(&mut &bytes[..20]).unwrap_err();
+    assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+}
@@ -0,0 +1,316 @@
+/*
+    Round-robin tournaments between agents, with Elo ratings.
+
+    Every pair of registered agents plays the same number of games, taking
+    turns to move first as Player::X. The results are turned into Elo
+    ratings: a difference of 400 points means the stronger agent is
+    expected to score 10 times as much as the weaker one.
+*/
+
+use std::f64::consts::LN_10;
+use std::fmt;
+
+use super::agent::{play_match, Agent};
+use super::rng::splitmix64;
+use super::{GameConfig, Player};
+
+// Results from one agent's point of view.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Record {
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl Record {
+    pub fn games(&self) -> usize {
+        self.wins + self.draws + self.losses
+    }
+    // Wins count 1, draws 1/2.
+    pub fn score(&self) -> f64 {
+        self.wins as f64 + self.draws as f64 / 2.0
+    }
+    // The same games from the opponent's point of view.
+    pub fn reversed(&self) -> Record {
+        Record { wins: self.losses, draws: self.draws, losses: self.wins }
+    }
+    fn add(&mut self, other: Record) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rating {
+    pub elo: f64,
+    // Half the width of a 95% confidence interval: elo +/- margin.
+    pub margin: f64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standings {
+    pub names: Vec<String>,
+    // records[i][j]: how agent i did against agent j.
+    pub records: Vec<Vec<Record>>,
+    pub ratings: Vec<Rating>,
+}
+
+impl Standings {
+    pub fn total(&self, agent: usize) -> Record {
+        let mut total = Record::default();
+        for &record in &self.records[agent] {
+            total.add(record);
+        }
+        total
+    }
+
+    // Agent indices, best rated first.
+    pub fn ranking(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|&a, &b| {
+            self.ratings[b].elo.partial_cmp(&self.ratings[a].elo).unwrap()
+        });
+        order
+    }
+}
+
+pub struct Tournament {
+    pub config: GameConfig,
+    pub games_per_pair: usize,
+    pub seed: u64,
+    agents: Vec<Box<dyn Agent>>,
+}
+
+impl Tournament {
+    pub fn new(config: GameConfig, games_per_pair: usize, seed: u64) -> Self {
+        Self { config, games_per_pair, seed, agents: Vec::new() }
+    }
+
+    pub fn register(&mut self, agent: Box<dyn Agent>) {
+        self.agents.push(agent);
+    }
+
+    pub fn run(&mut self) -> Standings {
+        let n = self.agents.len();
+        let mut records = vec![vec![Record::default(); n]; n];
+        let mut game_number = 0;
+        let pairs: Vec<(usize, usize)> =
+            (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j))).collect();
+        for (i, j) in pairs {
+            let record = self.play_pair(i, j, &mut game_number);
+            records[i][j] = record;
+            records[j][i] = record.reversed();
+        }
+        let names = self.agents.iter().map(|agent| agent.name()).collect();
+        let ratings = fit_ratings(&records);
+        Standings { names, records, ratings }
+    }
+
+    // Plays agent i (i < j) against agent j; returns i's record.
+    fn play_pair(
+        &mut self,
+        i: usize,
+        j: usize,
+        game_number: &mut u64,
+    ) -> Record {
+        let mut record = Record::default();
+        // Borrow two different elements of the Vec mutably at once by
+        // splitting it in two.
+        let (left, right) = self.agents.split_at_mut(j);
+        let (a, b) = (&mut left[i], &mut right[0]);
+        for round in 0..self.games_per_pair {
+            // Each game gets its own seed, so the results don't depend on
+            // anything but the tournament's seed.
+            *game_number += 1;
+            let seed = splitmix64(self.seed ^ *game_number);
+            a.reseed(seed);
+            b.reseed(seed.wrapping_add(1));
+            // Alternate who moves first.
+            let (a_plays, winner) = if round % 2 == 0 {
+                let result = play_match(&mut **a, &mut **b, self.config);
+                (Player::X, result.outcome.winner())
+            } else {
+                let result = play_match(&mut **b, &mut **a, self.config);
+                (Player::O, result.outcome.winner())
+            };
+            match winner {
+                None => record.draws += 1,
+                Some(player) if player == a_plays => record.wins += 1,
+                Some(_) => record.losses += 1,
+            }
+        }
+        record
+    }
+}
+
+// Expected score of a player rated `rating` against one rated `opponent`.
+fn expected(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+// Maximum likelihood Elo ratings, averaging 0.
+//
+// An agent that won (or lost) every game would have an infinite rating, so
+// every agent also gets one imaginary draw against a 0-rated opponent.
+// The margins come from the curvature of the likelihood: the more games,
+// and the closer they were, the more certain the rating.
+fn fit_ratings(records: &[Vec<Record>]) -> Vec<Rating> {
+    let n = records.len();
+    // d(expected)/d(rating) = SLOPE * e * (1 - e)
+    const SLOPE: f64 = LN_10 / 400.0;
+    let mut elo = vec![0.0; n];
+    for _ in 0..200 {
+        for i in 0..n {
+            let e = expected(elo[i], 0.0);
+            let mut surplus = 0.5 - e;
+            let mut information = e * (1.0 - e);
+            for j in 0..n {
+                let games = records[i][j].games() as f64;
+                let e = expected(elo[i], elo[j]);
+                surplus += records[i][j].score() - games * e;
+                information += games * e * (1.0 - e);
+            }
+            // One step of Newton's method.
+            elo[i] += surplus / (SLOPE * information);
+        }
+        let mean = elo.iter().sum::<f64>() / n as f64;
+        for rating in elo.iter_mut() {
+            *rating -= mean;
+        }
+    }
+    (0..n)
+        .map(|i| {
+            let e = expected(elo[i], 0.0);
+            let mut information = e * (1.0 - e);
+            for j in 0..n {
+                let e = expected(elo[i], elo[j]);
+                information += records[i][j].games() as f64 * e * (1.0 - e);
+            }
+            let std_error = 1.0 / (SLOPE * information.sqrt());
+            Rating { elo: elo[i], margin: 1.96 * std_error }
+        })
+        .collect()
+}
+
+// The standings report: ratings first, then who beat whom.
+impl fmt::Display for Standings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width =
+            self.names.iter().map(|name| name.len()).max().unwrap_or(0).max(5);
+        writeln!(
+            f,
+            "{:>4}  {:<width$}  {:>6}  {:>5}  {:>4}  {:>4}  {:>4}  {:>6}",
+            "Rank",
+            "Agent",
+            "Elo",
+            "+/-",
+            "W",
+            "D",
+            "L",
+            "Score",
+            width = width
+        )?;
+        for (rank, &i) in self.ranking().iter().enumerate() {
+            let total = self.total(i);
+            let percent = if total.games() == 0 {
+                0.0
+            } else {
+                100.0 * total.score() / total.games() as f64
+            };
+            writeln!(
+                f,
+                "{:>4}  {:<width$}  {:>6.0}  {:>5.0}  {:>4}  {:>4}  {:>4}  {:>5.1}%",
+                rank + 1,
+                self.names[i],
+                self.ratings[i].elo,
+                self.ratings[i].margin,
+                total.wins,
+                total.draws,
+                total.losses,
+                percent,
+                width = width
+            )?;
+        }
+        writeln!(f)?;
+        // Cross table: row agent's wins-draws-losses against column agent.
+        write!(f, "{:<width$}", "", width = width)?;
+        for name in &self.names {
+            write!(f, "  {:>width$}", name, width = width)?;
+        }
+        writeln!(f)?;
+        for (i, name) in self.names.iter().enumerate() {
+            write!(f, "{:<width$}", name, width = width)?;
+            for (j, record) in self.records[i].iter().enumerate() {
+                let cell = if i == j {
+                    "-".to_owned()
+                } else {
+                    format!(
+                        "{}-{}-{}",
+                        record.wins, record.draws, record.losses
+                    )
+                };
+                write!(f, "  {:>width$}", cell, width = width)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+fn test_tournament(seed: u64) -> Standings {
+    use super::agent::{FirstLegalAgent, RandomAgent, SearchAgent};
+    let mut tournament = Tournament::new(GameConfig::standard(), 6, seed);
+    tournament.register(Box::new(FirstLegalAgent));
+    tournament.register(Box::new(RandomAgent::new(0)));
+    tournament.register(Box::new(SearchAgent::new(3)));
+    tournament.run()
+}
+
+#[test]
+fn test_tournament_tables() {
+    let standings = test_tournament(1);
+    assert_eq!(standings.names, vec!["first-legal", "random", "search-3"]);
+    for i in 0..3 {
+        assert_eq!(standings.records[i][i], Record::default());
+        assert_eq!(standings.total(i).games(), 12);
+        for j in 0..3 {
+            let (a, b) = (standings.records[i][j], standings.records[j][i]);
+            assert_eq!(
+                (a.wins, a.draws, a.losses),
+                (b.losses, b.draws, b.wins)
+            );
+        }
+    }
+    assert_eq!(standings.ranking()[0], 2);
+    let sum: f64 = standings.ratings.iter().map(|rating| rating.elo).sum();
+    assert!(sum.abs() < 1e-6);
+    assert!(standings.ratings.iter().all(|rating| rating.margin > 0.0));
+}
+
+#[test]
+fn test_tournament_deterministic() {
+    let first = test_tournament(7);
+    assert_eq!(first, test_tournament(7));
+    assert_eq!(format!("{}", first), format!("{}", test_tournament(7)));
+}
+
+#[test]
+fn test_fit_ratings() {
+    // A scored 3/4 against B: Elo says that's about 191 points.
+    let a_vs_b = Record { wins: 300, draws: 0, losses: 100 };
+    let b_vs_a = Record { wins: 100, draws: 0, losses: 300 };
+    let records =
+        vec![vec![Record::default(), a_vs_b], vec![b_vs_a, Record::default()]];
+    let ratings = fit_ratings(&records);
+    let diff = ratings[0].elo - ratings[1].elo;
+    assert!((diff - 191.0).abs() < 5.0, "{}", diff);
+    assert!(ratings[0].margin < 50.0);
+    // Fewer games, less certainty.
+    let a_vs_b = Record { wins: 3, draws: 0, losses: 1 };
+    let b_vs_a = Record { wins: 1, draws: 0, losses: 3 };
+    let records =
+        vec![vec![Record::default(), a_vs_b], vec![b_vs_a, Record::default()]];
+    assert!(fit_ratings(&records)[0].margin > 100.0);
+}
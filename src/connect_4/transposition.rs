@@ -0,0 +1,122 @@
+/*
+    A transposition table: a fixed-size cache of search results, indexed by
+    Zobrist hash. Different move orders often reach the same position
+    ("transpositions"), and the table lets the engine search it only once.
+*/
+
+// Alpha-beta doesn't always find a position's exact score: a cutoff only
+// tells us the score is at least (Lower) or at most (Upper) some value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    Lower,
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Entry {
+    // The full hash, to tell apart positions that share a slot.
+    pub key: u64,
+    // How many plies deep the search below this position went.
+    pub depth: usize,
+    pub score: i32,
+    pub bound: Bound,
+    pub best: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TranspositionTable {
+    // Each slot remembers which search stored it, so that stale entries
+    // from earlier searches can be replaced first.
+    slots: Vec<Option<(Entry, u32)>>,
+    generation: u32,
+}
+
+impl TranspositionTable {
+    pub fn new(size: usize) -> Self {
+        assert!(size > 0, "empty transposition table");
+        Self { slots: vec![None; size], generation: 0 }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.slots.len() as u64) as usize
+    }
+
+    pub fn get(&self, key: u64) -> Option<Entry> {
+        match self.slots[self.index(key)] {
+            Some((entry, _)) if entry.key == key => Some(entry),
+            _ => None,
+        }
+    }
+
+    // Replacement policy: a new entry takes the slot if it is empty, holds
+    // the same position, was filled by an earlier search, or holds a
+    // shallower result. Deep results are expensive to recompute, so within
+    // one search we keep them over shallow ones.
+    pub fn store(&mut self, entry: Entry) {
+        let index = self.index(entry.key);
+        let replace = match self.slots[index] {
+            None => true,
+            Some((old, generation)) => {
+                old.key == entry.key
+                    || generation != self.generation
+                    || entry.depth >= old.depth
+            }
+        };
+        if replace {
+            self.slots[index] = Some((entry, self.generation));
+        }
+    }
+
+    // Call at the start of each search. Old entries stay usable, but no
+    // longer block new ones from their slots.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn clear(&mut self) {
+        for slot in self.slots.iter_mut() {
+            *slot = None;
+        }
+    }
+}
+
+#[cfg(test)]
+fn entry(key: u64, depth: usize) -> Entry {
+    Entry { key, depth, score: 0, bound: Bound::Exact, best: None }
+}
+
+#[test]
+fn test_store_and_get() {
+    let mut table = TranspositionTable::new(16);
+    table.store(entry(5, 3));
+    assert_eq!(table.get(5), Some(entry(5, 3)));
+    // Same slot, different position.
+    assert_eq!(table.get(21), None);
+    table.clear();
+    assert_eq!(table.get(5), None);
+}
+
+#[test]
+fn test_replacement() {
+    let mut table = TranspositionTable::new(16);
+    table.store(entry(5, 3));
+    // A shallower result for another position doesn't evict a deeper one...
+    table.store(entry(21, 2));
+    assert_eq!(table.get(5), Some(entry(5, 3)));
+    // ...but a deeper one does.
+    table.store(entry(21, 4));
+    assert_eq!(table.get(21), Some(entry(21, 4)));
+    // Results for the same position always replace.
+    table.store(entry(21, 1));
+    assert_eq!(table.get(21), Some(entry(21, 1)));
+    // And after a new search starts, so do shallow ones.
+    table.store(entry(37, 9));
+    table.new_search();
+    table.store(entry(5, 0));
+    assert_eq!(table.get(5), Some(entry(5, 0)));
+}
@@ -0,0 +1,41 @@
+/*
+    Zobrist hashing.
+
+    Give every (column, row, player) combination its own random 64-bit key;
+    the hash of a position is the XOR of the keys of all its pieces. Since
+    XOR is its own inverse, adding or removing a piece is a single XOR, no
+    matter how big the board is.
+
+    Instead of storing a table of random keys (whose size would depend on
+    the board), each key is computed on demand by scrambling its index.
+*/
+
+use super::rng::splitmix64;
+use super::Player;
+
+// XORed in when it's O's turn, so the same pieces with a different player
+// to move hash differently.
+pub const SIDE_TO_MOVE: u64 = 0x9e37_79b9_7f4a_7c15;
+
+pub fn piece(col: usize, row: usize, player: Player) -> u64 {
+    let player = match player {
+        Player::X => 0,
+        Player::O => 1,
+    };
+    splitmix64((col as u64) << 33 | (row as u64) << 1 | player)
+}
+
+#[test]
+fn test_keys_distinct() {
+    let mut keys = Vec::new();
+    for col in 0..16 {
+        for row in 0..16 {
+            keys.push(piece(col, row, Player::X));
+            keys.push(piece(col, row, Player::O));
+        }
+    }
+    keys.push(SIDE_TO_MOVE);
+    keys.sort_unstable();
+    keys.dedup();
+    assert_eq!(keys.len(), 16 * 16 * 2 + 1);
+}
@@ -3,6 +3,156 @@ pub mod enums;
 pub mod lifetimes;
 pub mod structs;
 
+use std::env;
+use std::io;
+use std::process;
+use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use connect_4::book::Book;
+use connect_4::play::{play, Seat};
+use connect_4::solver::Solution;
+use connect_4::{Game, GameConfig};
+
+const USAGE: &str = "\
+usage: code [--book FILE] [X] [O]
+       code --make-book FILE [PLIES] [DEPTH]
+       code --solve WIDTHxHEIGHT[xCONNECT] FILE
+    Each side is one of: human, random, first-legal, search[-DEPTH],
+    mcts[-PLAYOUTS]. The default is a human (X) against search (O).
+    With --book, search players take their opening moves from FILE.
+    --make-book searches every position up to PLIES moves in (default 3)
+    to DEPTH (at least 1; default 8) and saves the results to FILE.
+    --solve works out the result of perfect play from every position on
+    a small board, such as 4x4 or 5x4, and saves it to FILE. Boards of
+    more than 24 cells (6x5 and up) aren't supported.";
+
+fn usage() -> ! {
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
 fn main() {
     println!("CIS 198 Lecture 4");
+
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("--make-book") {
+        make_book(&args[1..]);
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("--solve") {
+        solve(&args[1..]);
+        return;
+    }
+    let mut book = None;
+    if args.first().map(|arg| arg.as_str()) == Some("--book") {
+        if args.len() < 2 {
+            usage();
+        }
+        match Book::load(&args[1]) {
+            Ok(loaded) => book = Some(Rc::new(loaded)),
+            Err(error) => {
+                eprintln!("can't read {}: {}", args[1], error);
+                process::exit(1);
+            }
+        }
+        args.drain(..2);
+    }
+    if args.len() > 2 || args.iter().any(|arg| arg.starts_with('-')) {
+        usage();
+    }
+    // Seed the random agents from the clock, so each game is different.
+    let seed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_nanos() as u64)
+        .unwrap_or(0);
+    let names = [
+        args.first().map_or("human", |arg| arg.as_str()),
+        args.get(1).map_or("search", |arg| arg.as_str()),
+    ];
+    let mut seats = Vec::new();
+    for (i, name) in names.iter().enumerate() {
+        let seed = seed.wrapping_add(i as u64);
+        let seat = match book {
+            Some(ref book) => Seat::parse_with_book(name, seed, book),
+            None => Seat::parse(name, seed),
+        };
+        match seat {
+            Some(seat) => seats.push(seat),
+            None => {
+                eprintln!("unknown player {:?}", name);
+                usage();
+            }
+        }
+    }
+    let mut o = seats.pop().unwrap();
+    let mut x = seats.pop().unwrap();
+
+    let stdin = io::stdin();
+    let config = GameConfig::default();
+    if let Err(error) = play(stdin.lock(), io::stdout(), config, &mut x, &mut o)
+    {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn make_book(args: &[String]) {
+    if args.is_empty() || args.len() > 3 {
+        usage();
+    }
+    let number = |i: usize, default: usize| match args.get(i) {
+        Some(arg) => arg.parse().unwrap_or_else(|_| usage()),
+        None => default,
+    };
+    let (plies, depth) = (number(1, 3), number(2, 8));
+    let book = match Book::generate(GameConfig::default(), plies, depth) {
+        Ok(book) => book,
+        Err(error) => {
+            eprintln!("{}", error);
+            usage();
+        }
+    };
+    if let Err(error) = book.save(&args[0]) {
+        eprintln!("can't write {}: {}", args[0], error);
+        process::exit(1);
+    }
+    println!("{} positions saved to {}", book.len(), args[0]);
+}
+
+fn solve(args: &[String]) {
+    if args.len() != 2 {
+        usage();
+    }
+    let sizes: Vec<usize> = args[0]
+        .split('x')
+        .map(|size| size.parse().unwrap_or_else(|_| usage()))
+        .collect();
+    let config = match sizes[..] {
+        [width, height] => GameConfig::new(width, height),
+        [width, height, connect] => {
+            GameConfig { connect, ..GameConfig::new(width, height) }
+        }
+        _ => usage(),
+    };
+    if sizes.contains(&0) {
+        usage();
+    }
+    let solution = match Solution::solve(config) {
+        Ok(solution) => solution,
+        Err(error) => {
+            eprintln!("{}", error);
+            process::exit(1);
+        }
+    };
+    let start = Game::with_config(config);
+    println!(
+        "{} positions; from the start: {:?}",
+        solution.len(),
+        solution.value(&start).unwrap()
+    );
+    if let Err(error) = solution.save(&args[1]) {
+        eprintln!("can't write {}: {}", args[1], error);
+        process::exit(1);
+    }
 }
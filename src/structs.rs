@@ -11,6 +11,7 @@ pub struct Computer {
     cpu_type: String,
 }
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct TwoComputers {
     comp1: Computer,
@@ -70,6 +71,7 @@ pub fn use_pair(p: Pair) {
 
 // Lets you as a programmer keep straight different things that should
 // be conceptually different
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct Area(f64);
 #[derive(Debug)]
@@ -235,6 +237,7 @@ impl MutExample {
 */
 use std::cell::Cell;
 
+#[allow(dead_code)]
 #[derive(Debug)]
 pub struct MutExampleBetter {
     field1: usize,